@@ -1,7 +1,259 @@
+use std::{fmt::Write, num::ParseIntError, str::FromStr};
+
+use crate::diagram::Diagram;
+use crate::movelist::MoveList;
 use crate::squareset::SquareSet;
-use crate::types::{Move, Square9x9, WallOrientation};
+use crate::squareset9x9::SquareSet as SquareSet9x9;
+use crate::types::{
+    Colour, GameResult, Move, Square8x8, Square9x9, SquareParseError, WallOrientation,
+};
+use crate::zobrist;
+
+/// A direction in which a pawn can step.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Direction {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Direction {
+    const ALL: [Self; 4] = [Self::North, Self::South, Self::East, Self::West];
+
+    /// Returns the two directions at right angles to this one.
+    const fn perpendicular(self) -> [Self; 2] {
+        match self {
+            Self::North | Self::South => [Self::West, Self::East],
+            Self::East | Self::West => [Self::South, Self::North],
+        }
+    }
+}
+
+/// The squares from which a pawn cannot step in each direction,
+/// either because of a wall or because of the edge of the board.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct BlockedEdges {
+    north: SquareSet9x9,
+    south: SquareSet9x9,
+    east: SquareSet9x9,
+    west: SquareSet9x9,
+}
+
+impl BlockedEdges {
+    /// The blocked edges of a board with no walls on it.
+    const BOARD_EDGES: Self = Self {
+        north: SquareSet9x9::RANK_9,
+        south: SquareSet9x9::RANK_1,
+        east: SquareSet9x9::I_FILE,
+        west: SquareSet9x9::A_FILE,
+    };
+
+    /// Computes the blocked edges of a board with the given walls from scratch.
+    fn from_walls(horizontal_walls: SquareSet, vertical_walls: SquareSet) -> Self {
+        let horizontal = embed_wall_set(horizontal_walls);
+        let vertical = embed_wall_set(vertical_walls);
+        // a horizontal wall blocks northward steps from the square of
+        // the same name and the square to its right, and southward
+        // steps from the two squares above those.
+        let north = horizontal | horizontal.east_one();
+        // a vertical wall blocks eastward steps from the square of
+        // the same name and the square above it, and westward steps
+        // from the two squares to the right of those.
+        let east = vertical | vertical.north_one();
+        Self {
+            north: Self::BOARD_EDGES.north | north,
+            south: Self::BOARD_EDGES.south | north.north_one(),
+            east: Self::BOARD_EDGES.east | east,
+            west: Self::BOARD_EDGES.west | east.east_one(),
+        }
+    }
+
+    /// Returns the squares from which a pawn cannot step in `direction`.
+    const fn towards(&self, direction: Direction) -> SquareSet9x9 {
+        match direction {
+            Direction::North => self.north,
+            Direction::South => self.south,
+            Direction::East => self.east,
+            Direction::West => self.west,
+        }
+    }
+
+    /// Blocks the edges crossed by the given wall.
+    fn add_wall(&mut self, to_square: Square8x8, orientation: WallOrientation) {
+        let wall = embed_wall_square(to_square);
+        match orientation {
+            WallOrientation::Horizontal => {
+                let north = wall | wall.east_one();
+                self.north |= north;
+                self.south |= north.north_one();
+            }
+            WallOrientation::Vertical => {
+                let east = wall | wall.north_one();
+                self.east |= east;
+                self.west |= east.east_one();
+            }
+        }
+    }
+
+    /// Unblocks the edges crossed by the given wall. As no two walls
+    /// may overlap, none of those edges are blocked by any other wall.
+    fn remove_wall(&mut self, to_square: Square8x8, orientation: WallOrientation) {
+        let wall = embed_wall_square(to_square);
+        match orientation {
+            WallOrientation::Horizontal => {
+                let north = wall | wall.east_one();
+                self.north -= north;
+                self.south -= north.north_one();
+            }
+            WallOrientation::Vertical => {
+                let east = wall | wall.north_one();
+                self.east -= east;
+                self.west -= east.east_one();
+            }
+        }
+    }
+
+    /// Returns the squares reachable from `reached` in a single step.
+    fn expand(&self, reached: SquareSet9x9) -> SquareSet9x9 {
+        reached
+            | (reached - self.north).north_one()
+            | (reached - self.south).south_one()
+            | (reached - self.east).east_one()
+            | (reached - self.west).west_one()
+    }
+
+    /// Returns every square that a pawn on `from` can walk to.
+    /// This is a flood fill over the board, where each iteration expands
+    /// the reached set by one step in every unblocked direction.
+    fn flood_fill(&self, from: Square9x9) -> SquareSet9x9 {
+        let mut reached = SquareSet9x9::from_square(from);
+        loop {
+            let next = self.expand(reached);
+            if next == reached {
+                return reached;
+            }
+            reached = next;
+        }
+    }
+
+    /// Determines whether a pawn on `from` can reach any square in `goal`.
+    /// As [`Self::flood_fill`], but stops as soon as the goal is reached.
+    fn has_path(&self, from: Square9x9, goal: SquareSet9x9) -> bool {
+        let mut reached = SquareSet9x9::from_square(from);
+        loop {
+            if !(reached & goal).is_empty() {
+                return true;
+            }
+            let next = self.expand(reached);
+            if next == reached {
+                return false;
+            }
+            reached = next;
+        }
+    }
+}
+
+/// Places an 8x8 set of wall centres onto the 9x9 board, such that
+/// each wall centre lands on the square to its south-west.
+fn embed_wall_set(walls: SquareSet) -> SquareSet9x9 {
+    let walls = walls.inner();
+    let mut inner = 0;
+    for rank in 0..8 {
+        let row = u128::from((walls >> (rank * 8)) & 0xFF);
+        inner |= row << (rank * 9);
+    }
+    SquareSet9x9::from_inner(inner)
+}
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+/// As [`embed_wall_set`], for a single wall centre.
+const fn embed_wall_square(square: Square8x8) -> SquareSet9x9 {
+    // the 9x9 index gains one for every rank below the square.
+    let index = square.index();
+    SquareSet9x9::from_inner(1 << (index + index / 8))
+}
+
+/// The inverse of [`embed_wall_set`]: returns the set of wall centres
+/// whose south-west squares are in `squares`.
+fn wall_centres_at(squares: SquareSet9x9) -> SquareSet {
+    let squares = squares.inner();
+    let mut inner = 0;
+    for rank in 0..8 {
+        #[allow(clippy::cast_possible_truncation)]
+        let row = ((squares >> (rank * 9)) & 0xFF) as u64;
+        inner |= row << (rank * 8);
+    }
+    SquareSet::from_inner(inner)
+}
+
+/// Returns the pairs of adjacent squares that the given wall separates,
+/// as indices into the 9x9 board.
+fn wall_cut_edges(to_square: Square8x8, orientation: WallOrientation) -> [(usize, usize); 2] {
+    let index = usize::from(to_square.index());
+    let south_west = index + index / 8;
+    match orientation {
+        WallOrientation::Horizontal => [
+            (south_west, south_west + 9),
+            (south_west + 1, south_west + 10),
+        ],
+        WallOrientation::Vertical => [
+            (south_west, south_west + 1),
+            (south_west + 9, south_west + 10),
+        ],
+    }
+}
+
+/// The length of the shortest path from each square to one player's
+/// goal row, taking walls into account but ignoring pawns.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DistanceMap {
+    distances: [u8; 81],
+}
+
+impl DistanceMap {
+    /// Marks the squares from which the goal row can't be reached.
+    const UNREACHABLE: u8 = u8::MAX;
+
+    /// Computes the map by a breadth-first search outward from `goal`,
+    /// expanding the searched region by a whole layer of squares at a time.
+    fn compute(blocked: &BlockedEdges, goal: SquareSet9x9) -> Self {
+        let mut distances = [Self::UNREACHABLE; 81];
+        let mut reached = goal;
+        let mut frontier = goal;
+        let mut distance = 0;
+        while !frontier.is_empty() {
+            for square in frontier {
+                distances[usize::from(square.index())] = distance;
+            }
+            frontier = blocked.expand(frontier) - reached;
+            reached |= frontier;
+            distance += 1;
+        }
+        Self { distances }
+    }
+
+    /// Returns the number of steps from `square` to the goal row,
+    /// or `None` if walls cut `square` off from the goal row.
+    #[must_use]
+    pub const fn get(&self, square: Square9x9) -> Option<u8> {
+        match self.distances[square.index() as usize] {
+            Self::UNREACHABLE => None,
+            distance => Some(distance),
+        }
+    }
+
+    /// Determines whether placing or taking back the given wall could
+    /// change the map. Only edges that join two different layers of the
+    /// search lie on shortest paths, so blocking or unblocking an edge
+    /// between two squares of the same distance changes nothing.
+    fn affected_by(&self, to_square: Square8x8, orientation: WallOrientation) -> bool {
+        wall_cut_edges(to_square, orientation)
+            .into_iter()
+            .any(|(a, b)| self.distances[a] != self.distances[b])
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Board {
     // we want a nice memory-efficient representation of the board
     // that also allows for fast move generation.
@@ -23,17 +275,38 @@ pub struct Board {
     walls_in_pocket: [u8; 2],
     /// Ply since the start of the game.
     ply: u16,
+    /// The Zobrist key of the position, maintained incrementally.
+    key: u64,
+    /// The edges between squares that pawns cannot cross,
+    /// maintained alongside the walls to speed up move generation.
+    blocked: BlockedEdges,
+    /// Each player's distance map, maintained alongside the walls.
+    distances: [DistanceMap; 2],
+}
+
+impl std::hash::Hash for Board {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // the key is a function of every other field but ply, the
+        // blocked edges, and the distance maps, the last two of which
+        // are functions of the walls, so equal boards have equal keys.
+        state.write_u64(self.key);
+    }
 }
 
 impl Default for Board {
     fn default() -> Self {
-        Self {
+        let mut board = Self {
             pawns: ["e1".parse().unwrap(), "e9".parse().unwrap()],
             horizontal_walls: SquareSet::default(),
             vertical_walls: SquareSet::default(),
-            walls_in_pocket: [10, 10],
+            walls_in_pocket: [Self::STARTING_WALLS; 2],
             ply: 0,
-        }
+            key: 0,
+            blocked: BlockedEdges::BOARD_EDGES,
+            distances: Self::compute_distances(&BlockedEdges::BOARD_EDGES),
+        };
+        board.key = board.compute_key();
+        board
     }
 }
 
@@ -58,108 +331,437 @@ impl std::fmt::Display for Board {
         // d4f4e7 / a2a8 / e4 e6 / 7 8 / 2
         let mut builder = String::new();
 
-        todo!()
+        for square in self.horizontal_walls {
+            write!(builder, "{square}")?;
+        }
+        builder.push_str(" / ");
+        for square in self.vertical_walls {
+            write!(builder, "{square}")?;
+        }
+        let [white, black] = self.pawns;
+        write!(builder, " / {white} {black}")?;
+        let [white, black] = self.walls_in_pocket;
+        write!(builder, " / {white} {black}")?;
+        write!(builder, " / {}", self.ply % 2 + 1)?;
+
+        write!(f, "{builder}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenParseError {
+    Empty,
+    WrongFieldCount(usize),
+    WallListLength(usize),
+    NonAsciiWallList,
+    WallSquareParseError(SquareParseError),
+    OverlappingWall(Move),
+    WrongPawnCount(usize),
+    PawnSquareParseError(SquareParseError),
+    PawnsOverlap(Square9x9),
+    WrongWallCountCount(usize),
+    WallCountParseError(ParseIntError),
+    WallCountOutOfRange(u8),
+    InvalidActivePlayer(String),
+}
+
+/// The reasons a move can be rejected by [`Board::try_make_move`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IllegalMoveError {
+    /// The game has already finished.
+    GameOver,
+    /// The pawn can't reach the target square in one move.
+    PawnNotReachable,
+    /// The player has no walls left to place.
+    OutOfWalls,
+    /// The wall would overlap a wall of the same orientation.
+    OccupiedSlot,
+    /// The wall would cross a wall of the other orientation.
+    CrossingWall,
+    /// The wall would leave a pawn with no path to its goal row.
+    SealsPath,
+}
+
+impl FromStr for Board {
+    type Err = FenParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_fen(s)
     }
 }
 
 impl Board {
+    /// The number of walls each player starts the game with.
+    pub const STARTING_WALLS: u8 = 10;
+
+    /// Parses a board from the FEN-like format emitted by [`Board`]'s `Display` implementation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the string is malformed, if any walls overlap,
+    /// if both pawns are on the same square, or if a player has more than
+    /// [`Board::STARTING_WALLS`] walls in their pocket.
+    pub fn from_fen(fen: &str) -> Result<Self, FenParseError> {
+        if fen.trim().is_empty() {
+            return Err(FenParseError::Empty);
+        }
+        let fields = fen.split('/').map(str::trim).collect::<Vec<_>>();
+        let &[horizontal, vertical, pawns, walls_in_pocket, active_player] = fields.as_slice() else {
+            return Err(FenParseError::WrongFieldCount(fields.len()));
+        };
+
+        let mut board = Self {
+            horizontal_walls: SquareSet::EMPTY,
+            vertical_walls: SquareSet::EMPTY,
+            ..Self::default()
+        };
+
+        for (list, orientation) in [
+            (horizontal, WallOrientation::Horizontal),
+            (vertical, WallOrientation::Vertical),
+        ] {
+            if !list.is_ascii() {
+                return Err(FenParseError::NonAsciiWallList);
+            }
+            if list.len() % 2 != 0 {
+                return Err(FenParseError::WallListLength(list.len()));
+            }
+            for i in (0..list.len()).step_by(2) {
+                let to_square = list[i..i + 2]
+                    .parse::<Square8x8>()
+                    .map_err(FenParseError::WallSquareParseError)?;
+                let blockers = match orientation {
+                    WallOrientation::Horizontal => board.horizontal_wall_blockers(),
+                    WallOrientation::Vertical => board.vertical_wall_blockers(),
+                };
+                if blockers.contains_square(to_square) {
+                    return Err(FenParseError::OverlappingWall(Move::Wall {
+                        to_square,
+                        orientation,
+                    }));
+                }
+                match orientation {
+                    WallOrientation::Horizontal => {
+                        board.horizontal_walls = board.horizontal_walls.add_square(to_square);
+                    }
+                    WallOrientation::Vertical => {
+                        board.vertical_walls = board.vertical_walls.add_square(to_square);
+                    }
+                }
+            }
+        }
+
+        let pawns = pawns.split_whitespace().collect::<Vec<_>>();
+        let &[white, black] = pawns.as_slice() else {
+            return Err(FenParseError::WrongPawnCount(pawns.len()));
+        };
+        for (pawn, square) in board.pawns.iter_mut().zip([white, black]) {
+            *pawn = square
+                .parse()
+                .map_err(FenParseError::PawnSquareParseError)?;
+        }
+        if board.pawns[0] == board.pawns[1] {
+            return Err(FenParseError::PawnsOverlap(board.pawns[0]));
+        }
+
+        let walls_in_pocket = walls_in_pocket.split_whitespace().collect::<Vec<_>>();
+        let &[white, black] = walls_in_pocket.as_slice() else {
+            return Err(FenParseError::WrongWallCountCount(walls_in_pocket.len()));
+        };
+        for (count, text) in board.walls_in_pocket.iter_mut().zip([white, black]) {
+            *count = text.parse().map_err(FenParseError::WallCountParseError)?;
+            if *count > Self::STARTING_WALLS {
+                return Err(FenParseError::WallCountOutOfRange(*count));
+            }
+        }
+
+        board.ply = match active_player {
+            "1" => 0,
+            "2" => 1,
+            other => return Err(FenParseError::InvalidActivePlayer(other.to_string())),
+        };
+        board.key = board.compute_key();
+        board.blocked = BlockedEdges::from_walls(board.horizontal_walls, board.vertical_walls);
+        board.distances = Self::compute_distances(&board.blocked);
+
+        Ok(board)
+    }
+
+    /// Determines whether a pawn standing on `square` could step one square in
+    /// `direction`, ignoring the other pawn. Returns the destination if the
+    /// step stays on the board and does not cross a wall.
+    const fn step(&self, square: Square9x9, direction: Direction) -> Option<Square9x9> {
+        if self.blocked.towards(direction).contains(square) {
+            return None;
+        }
+        let index = match direction {
+            Direction::North => square.index() + 9,
+            Direction::South => square.index() - 9,
+            Direction::East => square.index() + 1,
+            Direction::West => square.index() - 1,
+        };
+        // SAFETY: the blocked edges include the edges of the board,
+        // so the step can't leave the board.
+        Some(unsafe { Square9x9::from_index_unchecked(index) })
+    }
+
+    /// Returns the colour of the player to move.
+    #[must_use]
+    pub const fn turn(&self) -> Colour {
+        if self.ply.is_multiple_of(2) {
+            Colour::White
+        } else {
+            Colour::Black
+        }
+    }
+
+    /// Returns the result of the game, if either pawn has reached its goal row.
+    #[must_use]
+    pub const fn outcome(&self) -> Option<GameResult> {
+        if self.pawns[0].is_north_edge() {
+            Some(GameResult::WhiteWin)
+        } else if self.pawns[1].is_south_edge() {
+            Some(GameResult::BlackWin)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the set of squares occupied by pawns.
+    #[must_use]
+    pub fn pawn_occupancy(&self) -> SquareSet9x9 {
+        SquareSet9x9::from_square(self.pawns[0]) | SquareSet9x9::from_square(self.pawns[1])
+    }
+
+    /// Returns the set of squares that the given colour's pawn could walk to,
+    /// were it free to move as often as it liked. The other pawn is ignored,
+    /// as it can always be jumped over or walked around.
+    #[must_use]
+    pub fn reachable_squares(&self, colour: Colour) -> SquareSet9x9 {
+        self.blocked.flood_fill(self.pawns[colour.index()])
+    }
+
+    /// Returns the number of steps the given colour's pawn needs to reach its
+    /// goal row, ignoring the other pawn, or `None` if it has been cut off.
+    /// A pawn can only be cut off in positions that can't arise in play.
+    #[must_use]
+    pub const fn distance_to_goal(&self, colour: Colour) -> Option<u8> {
+        self.distances[colour.index()].get(self.pawns[colour.index()])
+    }
+
+    /// Returns the squares along one shortest path from the given colour's
+    /// pawn to its goal row, ignoring the other pawn, including both ends.
+    /// The path is empty if the pawn has been cut off from its goal row.
+    #[must_use]
+    pub fn shortest_path(&self, colour: Colour) -> SquareSet9x9 {
+        let map = &self.distances[colour.index()];
+        let mut square = self.pawns[colour.index()];
+        let Some(mut distance) = map.get(square) else {
+            return SquareSet9x9::new();
+        };
+        let mut path = SquareSet9x9::from_square(square);
+        // every square off the goal row has a neighbour one step closer to it.
+        while distance > 0 {
+            distance -= 1;
+            let Some(next) = Direction::ALL
+                .into_iter()
+                .filter_map(|direction| self.step(square, direction))
+                .find(|&next| map.get(next) == Some(distance))
+            else {
+                break;
+            };
+            square = next;
+            path = path.add(square);
+        }
+        path
+    }
+
+    /// Returns the number of distinct shortest paths from the given colour's
+    /// pawn to its goal row, ignoring the other pawn, or zero if it has been
+    /// cut off. The count saturates at `u64::MAX`.
     #[must_use]
-    pub fn from_fen(fen: &str) -> Self {
-        todo!()
+    pub fn shortest_path_count(&self, colour: Colour) -> u64 {
+        let map = &self.distances[colour.index()];
+        let pawn = self.pawns[colour.index()];
+        let Some(pawn_distance) = map.get(pawn) else {
+            return 0;
+        };
+        // walk forward from the pawn one step closer to the goal at a time,
+        // counting the paths from the pawn to each square on the way. every
+        // square that ends a shortest path is on the last of these layers.
+        let mut counts = [0u64; 81];
+        counts[usize::from(pawn.index())] = 1;
+        let mut layer = SquareSet9x9::from_square(pawn);
+        for distance in (0..pawn_distance).rev() {
+            let mut next_layer = SquareSet9x9::new();
+            for square in layer {
+                let count = counts[usize::from(square.index())];
+                for next in Direction::ALL
+                    .into_iter()
+                    .filter_map(|direction| self.step(square, direction))
+                    .filter(|&next| map.get(next) == Some(distance))
+                {
+                    let slot = &mut counts[usize::from(next.index())];
+                    *slot = slot.saturating_add(count);
+                    next_layer = next_layer.add(next);
+                }
+            }
+            layer = next_layer;
+        }
+        layer.into_iter().fold(0, |total: u64, square| {
+            total.saturating_add(counts[usize::from(square.index())])
+        })
+    }
+
+    /// Returns the centres of the walls that would touch a square on
+    /// either pawn's shortest path, as found by [`Self::shortest_path`].
+    pub(crate) fn walls_near_shortest_paths(&self) -> SquareSet {
+        let path = self.shortest_path(Colour::White) | self.shortest_path(Colour::Black);
+        // a wall touches the squares to the north-east, north-west, south-east,
+        // and south-west of its centre, the last of which shares its name.
+        let touched = path | path.west_one() | path.south_one() | path.south_one().west_one();
+        wall_centres_at(touched)
     }
 
+    /// Returns the given colour's distance map, which holds the number of
+    /// steps from every square on the board to that colour's goal row.
+    #[must_use]
+    pub const fn distance_map(&self, colour: Colour) -> &DistanceMap {
+        &self.distances[colour.index()]
+    }
+
+    /// Generates the legal moves in this position, passing each to `callback`.
+    /// Generation stops early if `callback` returns `true`.
+    /// No moves are generated once the game is over.
     pub fn generate_moves(&self, mut callback: impl FnMut(Move) -> bool) {
+        if self.outcome().is_some() {
+            return;
+        }
+
+        if self.generate_pawn_moves(&mut callback) {
+            return;
+        }
+        self.generate_wall_moves(SquareSet::FULL, &mut callback);
+    }
+
+    /// Returns the legal moves in this position, in the order
+    /// that [`Self::generate_moves`] generates them.
+    #[must_use]
+    pub fn legal_moves(&self) -> MoveList {
+        let mut moves = MoveList::new();
+        self.generate_moves(|mv| {
+            moves.push(mv);
+            false
+        });
+        moves
+    }
+
+    /// Generates the legal pawn moves in this position, passing each to `callback`.
+    /// Returns `true` if `callback` stopped generation early.
+    pub(crate) fn generate_pawn_moves(&self, mut callback: impl FnMut(Move) -> bool) -> bool {
         let turn_index = usize::from(self.ply % 2);
         let pawn = self.pawns[turn_index];
         let opponent_pawn = self.pawns[1 - turn_index];
 
-        // generate pawn moves
-        if let Some(to_square) = pawn.above() {
-            let wall_here = pawn.try_into().map_or(false, |pawn| {
-                self.horizontal_walls.contains_square(pawn)
-            });
-            let wall_to_the_left = pawn.left().map_or(false, |left| {
-                left.try_into().map_or(false, |pawn| {
-                    self.horizontal_walls.contains_square(pawn)
-                })
-            });
-            // if there isn't a wall blocking us, we pass the move to the callback
-            if !wall_here && !wall_to_the_left && callback(Move::Pawn { to_square }) {
-                return;
-            }
-        }
-        if let Some(to_square) = pawn.below() {
-            let wall_over = to_square.try_into().map_or(false, |pawn| {
-                self.horizontal_walls.contains_square(pawn)
-            });
-            let wall_to_the_left = to_square.left().map_or(false, |left| {
-                left.try_into().map_or(false, |pawn| {
-                    self.horizontal_walls.contains_square(pawn)
-                })
-            });
-            // if there isn't a wall blocking us, we pass the move to the callback
-            if !wall_over && !wall_to_the_left && callback(Move::Pawn { to_square }) {
-                return;
-            }
-        }
-        if let Some(to_square) = pawn.right() {
-            let wall_here = pawn.try_into().map_or(false, |pawn| {
-                self.vertical_walls.contains_square(pawn)
-            });
-            let wall_below = pawn.below().map_or(false, |below| {
-                below.try_into().map_or(false, |pawn| {
-                    self.vertical_walls.contains_square(pawn)
-                })
-            });
-            // if there isn't a wall blocking us, we pass the move to the callback
-            if !wall_here && !wall_below && callback(Move::Pawn { to_square }) {
-                return;
-            }
-        }
-        if let Some(to_square) = pawn.left() {
-            let wall_over = to_square.try_into().map_or(false, |pawn| {
-                self.vertical_walls.contains_square(pawn)
-            });
-            let wall_below = to_square.below().map_or(false, |below| {
-                below.try_into().map_or(false, |pawn| {
-                    self.vertical_walls.contains_square(pawn)
-                })
-            });
-            // if there isn't a wall blocking us, we pass the move to the callback
-            if !wall_over && !wall_below && callback(Move::Pawn { to_square }) {
-                return;
-            }
-        }
-
-        // generate wall moves
+        for direction in Direction::ALL {
+            let Some(to_square) = self.step(pawn, direction) else {
+                continue;
+            };
+            // if the opponent isn't in the way, we pass the move to the callback
+            if to_square != opponent_pawn {
+                if callback(Move::Pawn { to_square }) {
+                    return true;
+                }
+                continue;
+            }
+            // the opponent is directly in front of us, so we may jump straight
+            // over them, if there isn't a wall or the edge of the board behind them.
+            if let Some(to_square) = self.step(opponent_pawn, direction) {
+                if callback(Move::Pawn { to_square }) {
+                    return true;
+                }
+                continue;
+            }
+            // otherwise, we may jump diagonally to either side of the opponent,
+            // so long as there isn't a wall or the edge of the board in the way.
+            for side in direction.perpendicular() {
+                if let Some(to_square) = self.step(opponent_pawn, side) {
+                    if callback(Move::Pawn { to_square }) {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Generates the legal wall moves centred on `candidates` in this position,
+    /// passing each to `callback`. Returns `true` if `callback` stopped generation early.
+    pub(crate) fn generate_wall_moves(
+        &self,
+        candidates: SquareSet,
+        mut callback: impl FnMut(Move) -> bool,
+    ) -> bool {
+        let turn_index = usize::from(self.ply % 2);
         let our_wall_count = self.walls_in_pocket[turn_index];
         if our_wall_count == 0 {
-            return;
+            return false;
         }
 
-        // generate horizontal wall moves
-        // these moves are blocked by
-        // 1. the existing horizontal walls
-        let mut blockers = self.horizontal_walls;
-        // 2. the right-hand sides of existing horizontal walls
-        blockers |= blockers.east_one();
-        // 3. the left-hand sides of existing horizontal walls
-        blockers |= blockers.west_one();
-        // 4. the middles of existing vertical walls
-        blockers |= self.vertical_walls;
+        // walls that would seal either pawn off from its goal row are illegal,
+        // but a single wall on an otherwise empty board can never seal a pawn
+        // off, so if the board is empty of walls we can skip the check entirely.
+        let needs_path_check = self.horizontal_walls.non_empty() || self.vertical_walls.non_empty();
 
-        let moves = !blockers;
+        // generate horizontal wall moves
+        let moves = candidates - self.horizontal_wall_blockers();
         for to_square in moves {
+            if needs_path_check && self.seals_path(to_square, WallOrientation::Horizontal) {
+                continue;
+            }
             if callback(Move::Wall {
                 to_square,
                 orientation: WallOrientation::Horizontal,
             }) {
-                return;
+                return true;
             }
         }
 
         // generate vertical wall moves
-        // these moves are blocked by
+        let moves = candidates - self.vertical_wall_blockers();
+        for to_square in moves {
+            if needs_path_check && self.seals_path(to_square, WallOrientation::Vertical) {
+                continue;
+            }
+            if callback(Move::Wall {
+                to_square,
+                orientation: WallOrientation::Vertical,
+            }) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Returns the set of squares on which a horizontal wall would overlap an existing wall.
+    fn horizontal_wall_blockers(&self) -> SquareSet {
+        // horizontal walls are blocked by
+        // 1. the existing horizontal walls
+        let mut blockers = self.horizontal_walls;
+        // 2. the right-hand sides of existing horizontal walls
+        blockers |= blockers.east_one();
+        // 3. the left-hand sides of existing horizontal walls
+        blockers |= blockers.west_one();
+        // 4. the middles of existing vertical walls
+        blockers |= self.vertical_walls;
+        blockers
+    }
+
+    /// Returns the set of squares on which a vertical wall would overlap an existing wall.
+    fn vertical_wall_blockers(&self) -> SquareSet {
+        // vertical walls are blocked by
         // 1. the existing vertical walls
         let mut blockers = self.vertical_walls;
         // 2. the top sides of existing vertical walls
@@ -168,26 +770,182 @@ impl Board {
         blockers |= blockers.south_one();
         // 4. the middles of existing horizontal walls
         blockers |= self.horizontal_walls;
+        blockers
+    }
 
-        let moves = !blockers;
-        for to_square in moves {
-            if callback(Move::Wall {
+    /// Determines whether placing the given wall would leave either pawn
+    /// with no path to its goal row.
+    fn seals_path(&self, to_square: Square8x8, orientation: WallOrientation) -> bool {
+        for colour in [Colour::White, Colour::Black] {
+            let map = &self.distances[colour.index()];
+            let pawn = self.pawns[colour.index()];
+            // a wall that leaves the distance map untouched can't
+            // lengthen the pawn's path, let alone cut it off.
+            if map.get(pawn).is_some() && !map.affected_by(to_square, orientation) {
+                continue;
+            }
+            let mut blocked = self.blocked;
+            blocked.add_wall(to_square, orientation);
+            if !blocked.has_path(pawn, colour.goal_row()) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Determines whether the side to move could place the given wall:
+    /// they have a wall left, the wall doesn't overlap any existing wall,
+    /// and it doesn't seal either pawn off from its goal row.
+    #[must_use]
+    pub fn is_legal_wall(&self, to_square: Square8x8, orientation: WallOrientation) -> bool {
+        self.is_legal(Move::Wall {
+            to_square,
+            orientation,
+        })
+    }
+
+    /// Determines whether the given move is legal in this position.
+    #[must_use]
+    pub fn is_legal(&self, mv: Move) -> bool {
+        self.check_legality(mv).is_ok()
+    }
+
+    /// Makes the given move if it is legal in this position,
+    /// and otherwise leaves the board untouched.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing why the move is illegal.
+    pub fn try_make_move(&mut self, mv: Move) -> Result<(), IllegalMoveError> {
+        self.check_legality(mv)?;
+        self.make_move(mv);
+        Ok(())
+    }
+
+    /// Checks whether the given move is legal in this position,
+    /// returning the reason it is illegal if not.
+    fn check_legality(&self, mv: Move) -> Result<(), IllegalMoveError> {
+        if self.outcome().is_some() {
+            return Err(IllegalMoveError::GameOver);
+        }
+        match mv {
+            Move::Pawn { .. } => {
+                let mut reachable = false;
+                self.generate_pawn_moves(|pawn_move| {
+                    reachable = pawn_move == mv;
+                    reachable
+                });
+                if reachable {
+                    Ok(())
+                } else {
+                    Err(IllegalMoveError::PawnNotReachable)
+                }
+            }
+            Move::Wall {
                 to_square,
-                orientation: WallOrientation::Vertical,
-            }) {
-                return;
+                orientation,
+            } => {
+                let turn_index = usize::from(self.ply % 2);
+                if self.walls_in_pocket[turn_index] == 0 {
+                    return Err(IllegalMoveError::OutOfWalls);
+                }
+                // the blockers for a wall include the centres of walls
+                // of the other orientation, so check for crossings first.
+                let (crossing_walls, blockers) = match orientation {
+                    WallOrientation::Horizontal => {
+                        (self.vertical_walls, self.horizontal_wall_blockers())
+                    }
+                    WallOrientation::Vertical => {
+                        (self.horizontal_walls, self.vertical_wall_blockers())
+                    }
+                };
+                if crossing_walls.contains_square(to_square) {
+                    return Err(IllegalMoveError::CrossingWall);
+                }
+                if blockers.contains_square(to_square) {
+                    return Err(IllegalMoveError::OccupiedSlot);
+                }
+                if self.seals_path(to_square, orientation) {
+                    return Err(IllegalMoveError::SealsPath);
+                }
+                Ok(())
             }
         }
     }
 
+    /// Sets the number of walls each player has left to place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either count exceeds [`Board::STARTING_WALLS`].
     pub fn set_walls_in_pocket(&mut self, white: u8, black: u8) {
+        assert!(
+            white <= Self::STARTING_WALLS && black <= Self::STARTING_WALLS,
+            "walls in pocket must not exceed {}",
+            Self::STARTING_WALLS
+        );
+        for (index, count) in [white, black].into_iter().enumerate() {
+            self.key ^=
+                zobrist::WALLS_IN_POCKET_KEYS[index][usize::from(self.walls_in_pocket[index])];
+            self.key ^= zobrist::WALLS_IN_POCKET_KEYS[index][usize::from(count)];
+        }
         self.walls_in_pocket = [white, black];
     }
 
+    /// Returns the Zobrist key of the position, which identifies the pawn
+    /// squares, the walls on the board, the walls in each player's pocket,
+    /// and the side to move.
+    #[must_use]
+    pub const fn hash_key(&self) -> u64 {
+        self.key
+    }
+
+    /// Computes both players' distance maps from scratch.
+    fn compute_distances(blocked: &BlockedEdges) -> [DistanceMap; 2] {
+        [Colour::White, Colour::Black]
+            .map(|colour| DistanceMap::compute(blocked, colour.goal_row()))
+    }
+
+    /// Recomputes whichever distance maps could have been changed
+    /// by the given wall, which has just been placed or taken back.
+    fn refresh_distances(&mut self, to_square: Square8x8, orientation: WallOrientation) {
+        for colour in [Colour::White, Colour::Black] {
+            let map = &mut self.distances[colour.index()];
+            if map.affected_by(to_square, orientation) {
+                *map = DistanceMap::compute(&self.blocked, colour.goal_row());
+            }
+        }
+    }
+
+    /// Computes the Zobrist key of the position from scratch.
+    fn compute_key(&self) -> u64 {
+        let mut key = 0;
+        for (index, pawn) in self.pawns.iter().enumerate() {
+            key ^= zobrist::PAWN_KEYS[index][usize::from(pawn.index())];
+        }
+        for square in self.horizontal_walls {
+            key ^= zobrist::WALL_KEYS[0][usize::from(square.index())];
+        }
+        for square in self.vertical_walls {
+            key ^= zobrist::WALL_KEYS[1][usize::from(square.index())];
+        }
+        for (index, &count) in self.walls_in_pocket.iter().enumerate() {
+            key ^= zobrist::WALLS_IN_POCKET_KEYS[index][usize::from(count)];
+        }
+        if self.ply % 2 == 1 {
+            key ^= zobrist::SIDE_TO_MOVE_KEY;
+        }
+        key
+    }
+
     pub fn make_move(&mut self, mv: Move) {
+        debug_assert!(self.outcome().is_none(), "tried to make move {mv} after the game ended");
         match mv {
             Move::Pawn { to_square } => {
                 let turn_index = usize::from(self.ply % 2);
+                let keys = &zobrist::PAWN_KEYS[turn_index];
+                self.key ^= keys[usize::from(self.pawns[turn_index].index())];
+                self.key ^= keys[usize::from(to_square.index())];
                 self.pawns[turn_index] = to_square;
             }
             Move::Wall {
@@ -195,7 +953,18 @@ impl Board {
                 orientation,
             } => {
                 let turn_index = usize::from(self.ply % 2);
+                debug_assert!(
+                    self.walls_in_pocket[turn_index] > 0,
+                    "tried to place wall {mv} with no walls left"
+                );
+                let keys = &zobrist::WALLS_IN_POCKET_KEYS[turn_index];
+                self.key ^= keys[usize::from(self.walls_in_pocket[turn_index])];
                 self.walls_in_pocket[turn_index] -= 1;
+                self.key ^= keys[usize::from(self.walls_in_pocket[turn_index])];
+                self.key ^=
+                    zobrist::WALL_KEYS[orientation as usize][usize::from(to_square.index())];
+                self.blocked.add_wall(to_square, orientation);
+                self.refresh_distances(to_square, orientation);
                 match orientation {
                     WallOrientation::Horizontal => {
                         self.horizontal_walls = self.horizontal_walls.add_square(to_square);
@@ -207,9 +976,476 @@ impl Board {
             }
         }
         self.ply += 1;
+        self.key ^= zobrist::SIDE_TO_MOVE_KEY;
+    }
+
+    /// Takes back `mv`, which must be the last move made on this board.
+    /// `pawn_from` is the square the moving pawn stood on before `mv`
+    /// was made, and is ignored when taking back a wall.
+    pub fn unmake_move(&mut self, mv: Move, pawn_from: Square9x9) {
+        debug_assert!(
+            self.ply > 0,
+            "tried to unmake move {mv} at the start of the game"
+        );
+        self.ply -= 1;
+        self.key ^= zobrist::SIDE_TO_MOVE_KEY;
+        let turn_index = usize::from(self.ply % 2);
+        match mv {
+            Move::Pawn { to_square } => {
+                debug_assert_eq!(
+                    self.pawns[turn_index], to_square,
+                    "pawn isn't on the square it moved to"
+                );
+                let keys = &zobrist::PAWN_KEYS[turn_index];
+                self.key ^= keys[usize::from(to_square.index())];
+                self.key ^= keys[usize::from(pawn_from.index())];
+                self.pawns[turn_index] = pawn_from;
+            }
+            Move::Wall {
+                to_square,
+                orientation,
+            } => {
+                let keys = &zobrist::WALLS_IN_POCKET_KEYS[turn_index];
+                self.key ^= keys[usize::from(self.walls_in_pocket[turn_index])];
+                self.walls_in_pocket[turn_index] += 1;
+                self.key ^= keys[usize::from(self.walls_in_pocket[turn_index])];
+                self.key ^=
+                    zobrist::WALL_KEYS[orientation as usize][usize::from(to_square.index())];
+                self.blocked.remove_wall(to_square, orientation);
+                self.refresh_distances(to_square, orientation);
+                match orientation {
+                    WallOrientation::Horizontal => {
+                        debug_assert!(
+                            self.horizontal_walls.contains_square(to_square),
+                            "wall {mv} isn't on the board"
+                        );
+                        self.horizontal_walls = self.horizontal_walls.remove_square(to_square);
+                    }
+                    WallOrientation::Vertical => {
+                        debug_assert!(
+                            self.vertical_walls.contains_square(to_square),
+                            "wall {mv} isn't on the board"
+                        );
+                        self.vertical_walls = self.vertical_walls.remove_square(to_square);
+                    }
+                }
+            }
+        }
     }
 
-    pub fn pass_turn(&mut self) {
+    pub const fn pass_turn(&mut self) {
         self.ply += 1;
+        self.key ^= zobrist::SIDE_TO_MOVE_KEY;
+    }
+
+    /// Returns the square of the given player's pawn.
+    #[must_use]
+    pub const fn pawn(&self, colour: Colour) -> Square9x9 {
+        self.pawns[colour.index()]
+    }
+
+    /// Determines whether a wall has been placed with the given centre and orientation.
+    #[must_use]
+    pub const fn has_wall(&self, to_square: Square8x8, orientation: WallOrientation) -> bool {
+        match orientation {
+            WallOrientation::Horizontal => self.horizontal_walls.contains_square(to_square),
+            WallOrientation::Vertical => self.vertical_walls.contains_square(to_square),
+        }
+    }
+
+    /// Returns a diagram of the board, which can be styled and annotated
+    /// before being displayed.
+    #[must_use]
+    pub const fn diagram(&self) -> Diagram<'_> {
+        Diagram::new(self)
+    }
+
+    /// Returns the number of walls the given player has left to place.
+    #[must_use]
+    pub const fn walls_in_pocket(&self, colour: Colour) -> u8 {
+        self.walls_in_pocket[colour.index()]
+    }
+
+    /// Returns the number of plies played since the start of the game.
+    #[must_use]
+    pub const fn ply(&self) -> u16 {
+        self.ply
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Board, FenParseError, IllegalMoveError};
+    use crate::rng::XorShiftRng;
+    use crate::types::{Move, SquareParseError};
+
+    /// Plays up to `plies` uniformly random legal moves from the starting position,
+    /// returning every position reached along the way.
+    fn random_game(rng: &mut XorShiftRng, plies: usize) -> Vec<Board> {
+        let mut board = Board::default();
+        let mut positions = vec![board];
+        for _ in 0..plies {
+            let moves = board.legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            board.make_move(moves[rng.next_below(moves.len())]);
+            positions.push(board);
+        }
+        positions
+    }
+
+    #[test]
+    fn fen_startpos() {
+        let board = Board::default();
+        assert_eq!(board.to_string(), " /  / e1 e9 / 10 10 / 1");
+        assert_eq!(Board::from_fen(&board.to_string()), Ok(board));
+    }
+
+    #[test]
+    fn fen_example() {
+        let fen = "d4f4e7 / a2a8 / e4 e6 / 7 8 / 2";
+        let board: Board = fen.parse().unwrap();
+        assert_eq!(board.to_string(), fen);
+
+        let mut moves = Vec::new();
+        board.generate_moves(|mv| {
+            moves.push(mv.to_string());
+            false
+        });
+        // black is on e6, white is on e4, and e5 is free.
+        assert!(moves.contains(&"e5".to_string()));
+        assert!(!moves.contains(&"d4h".to_string()), "d4h is already occupied");
+        assert!(!moves.contains(&"e4h".to_string()), "e4h overlaps d4h and f4h");
+        assert!(!moves.contains(&"a3v".to_string()), "a3v overlaps a2v");
+    }
+
+    #[test]
+    fn fen_errors() {
+        assert_eq!(Board::from_fen(""), Err(FenParseError::Empty));
+        assert_eq!(
+            Board::from_fen(" /  / e1 e9 / 10 10"),
+            Err(FenParseError::WrongFieldCount(4))
+        );
+        assert_eq!(
+            Board::from_fen("d4f / / e1 e9 / 10 10 / 1"),
+            Err(FenParseError::WallListLength(3))
+        );
+        assert_eq!(
+            Board::from_fen("dé / / e1 e9 / 10 10 / 1"),
+            Err(FenParseError::NonAsciiWallList)
+        );
+        assert_eq!(
+            Board::from_fen(" / éé / e1 e9 / 10 10 / 1"),
+            Err(FenParseError::NonAsciiWallList)
+        );
+        assert_eq!(
+            Board::from_fen("d9 / / e1 e9 / 10 10 / 1"),
+            Err(FenParseError::WallSquareParseError(
+                SquareParseError::RankOutOfRange('9')
+            ))
+        );
+        assert_eq!(
+            Board::from_fen("d4e4 / / e1 e9 / 10 10 / 1"),
+            Err(FenParseError::OverlappingWall("e4h".parse::<Move>().unwrap()))
+        );
+        assert_eq!(
+            Board::from_fen("d4 / d4 / e1 e9 / 10 10 / 1"),
+            Err(FenParseError::OverlappingWall("d4v".parse::<Move>().unwrap()))
+        );
+        assert_eq!(
+            Board::from_fen(" /  / e1 / 10 10 / 1"),
+            Err(FenParseError::WrongPawnCount(1))
+        );
+        assert_eq!(
+            Board::from_fen(" /  / e1 j9 / 10 10 / 1"),
+            Err(FenParseError::PawnSquareParseError(
+                SquareParseError::FileOutOfRange('j')
+            ))
+        );
+        assert_eq!(
+            Board::from_fen(" /  / e5 e5 / 10 10 / 1"),
+            Err(FenParseError::PawnsOverlap("e5".parse().unwrap()))
+        );
+        assert_eq!(
+            Board::from_fen(" /  / e1 e9 / 10 / 1"),
+            Err(FenParseError::WrongWallCountCount(1))
+        );
+        assert!(matches!(
+            Board::from_fen(" /  / e1 e9 / 10 x / 1"),
+            Err(FenParseError::WallCountParseError(_))
+        ));
+        assert_eq!(
+            Board::from_fen(" /  / e1 e9 / 10 11 / 1"),
+            Err(FenParseError::WallCountOutOfRange(11))
+        );
+        assert_eq!(
+            Board::from_fen(" /  / e1 e9 / 10 10 / 3"),
+            Err(FenParseError::InvalidActivePlayer("3".to_string()))
+        );
+    }
+
+    #[test]
+    fn fen_round_trip_random_games() {
+        let mut rng = XorShiftRng::new(0x5EED);
+        for _ in 0..50 {
+            for board in random_game(&mut rng, 60) {
+                let fen = board.to_string();
+                let parsed = Board::from_fen(&fen)
+                    .unwrap_or_else(|e| panic!("failed to parse {fen:?}: {e:?}"));
+                assert_eq!(parsed.to_string(), fen);
+                // the FEN doesn't record the number of plies played,
+                // only whose turn it is, so everything else must match.
+                assert_eq!(parsed, Board { ply: board.ply % 2, ..board });
+            }
+        }
+    }
+
+    #[test]
+    fn illegal_move_reasons() {
+        let mut board: Board = "e4 / d6 / e5 e6 / 0 10 / 1".parse().unwrap();
+        let mv = |s: &str| s.parse::<Move>().unwrap();
+
+        assert_eq!(
+            board.try_make_move(mv("e3h")),
+            Err(IllegalMoveError::OutOfWalls)
+        );
+        assert_eq!(
+            board.try_make_move(mv("e9")),
+            Err(IllegalMoveError::PawnNotReachable)
+        );
+        // black is in the way, and e4h is behind white.
+        assert_eq!(
+            board.try_make_move(mv("e6")),
+            Err(IllegalMoveError::PawnNotReachable)
+        );
+        assert_eq!(
+            board.try_make_move(mv("e4")),
+            Err(IllegalMoveError::PawnNotReachable)
+        );
+        // but white can jump straight over black.
+        assert_eq!(board.try_make_move(mv("e7")), Ok(()));
+
+        // now black is to move, with all their walls.
+        assert_eq!(
+            board.try_make_move(mv("f4h")),
+            Err(IllegalMoveError::OccupiedSlot)
+        );
+        assert_eq!(
+            board.try_make_move(mv("e4h")),
+            Err(IllegalMoveError::OccupiedSlot)
+        );
+        assert_eq!(
+            board.try_make_move(mv("e4v")),
+            Err(IllegalMoveError::CrossingWall)
+        );
+        assert_eq!(
+            board.try_make_move(mv("d5v")),
+            Err(IllegalMoveError::OccupiedSlot)
+        );
+        assert_eq!(
+            board.try_make_move(mv("d6h")),
+            Err(IllegalMoveError::CrossingWall)
+        );
+        assert!(!board.is_legal(mv("d6h")));
+        assert!(board.is_legal(mv("a1h")));
+
+        // rejected moves must leave the board untouched.
+        assert_eq!(board.to_string(), "e4 / d6 / e7 e6 / 0 10 / 2");
+    }
+
+    #[test]
+    fn illegal_move_seals_path() {
+        let mut board: Board = "a5c5e5g5 / h5 / e1 e9 / 5 10 / 1".parse().unwrap();
+        assert_eq!(
+            board.try_make_move("h6h".parse().unwrap()),
+            Err(IllegalMoveError::SealsPath)
+        );
+    }
+
+    #[test]
+    fn illegal_move_game_over() {
+        let mut board: Board = " /  / e9 e2 / 10 10 / 2".parse().unwrap();
+        assert_eq!(
+            board.try_make_move("e1".parse().unwrap()),
+            Err(IllegalMoveError::GameOver)
+        );
+    }
+
+    #[test]
+    fn is_legal_agrees_with_movegen() {
+        use crate::types::{Square8x8, Square9x9, WallOrientation};
+
+        let mut every_move = Vec::new();
+        for index in 0..81 {
+            let to_square = Square9x9::from_index(index).unwrap();
+            every_move.push(Move::Pawn { to_square });
+        }
+        for index in 0..64 {
+            let to_square = Square8x8::from_index(index).unwrap();
+            for orientation in [WallOrientation::Horizontal, WallOrientation::Vertical] {
+                every_move.push(Move::Wall {
+                    to_square,
+                    orientation,
+                });
+            }
+        }
+
+        let mut rng = XorShiftRng::new(0x1E6A1);
+        for _ in 0..20 {
+            for board in random_game(&mut rng, 60) {
+                let legal = board.legal_moves();
+                for &mv in &every_move {
+                    assert_eq!(
+                        board.is_legal(mv),
+                        legal.contains(mv),
+                        "legality of {mv} disagrees with movegen in {board}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn occupancy_and_reachability() {
+        use crate::squareset9x9::SquareSet as SquareSet9x9;
+        use crate::types::{Colour, Square9x9};
+
+        let square = |s: &str| s.parse::<Square9x9>().unwrap();
+        let set = |squares: &[&str]| {
+            squares
+                .iter()
+                .map(|s| square(s))
+                .fold(SquareSet9x9::new(), SquareSet9x9::add)
+        };
+
+        let board = Board::default();
+        assert_eq!(board.pawn_occupancy(), set(&["e1", "e9"]));
+        assert_eq!(board.reachable_squares(Colour::White).count(), 81);
+        assert!((Colour::White.goal_row() & Colour::Black.goal_row()).is_empty());
+        assert_eq!(Colour::White.goal_row().count(), 9);
+
+        // a2h and b1v box in the bottom-left corner of the board.
+        let board: Board = "a2 / b1 / a1 e9 / 8 8 / 1".parse().unwrap();
+        let corner = set(&["a1", "b1", "a2", "b2"]);
+        assert_eq!(board.reachable_squares(Colour::White), corner);
+        assert_eq!(board.reachable_squares(Colour::Black), !corner);
+        assert!((board.reachable_squares(Colour::White) & Colour::White.goal_row()).is_empty());
+    }
+
+    #[test]
+    fn derived_state_incremental() {
+        use super::BlockedEdges;
+
+        let mut rng = XorShiftRng::new(0xED6E);
+        for _ in 0..20 {
+            let positions = random_game(&mut rng, 80);
+            for pair in positions.windows(2) {
+                let (before, after) = (pair[0], pair[1]);
+                let from_scratch =
+                    BlockedEdges::from_walls(after.horizontal_walls, after.vertical_walls);
+                assert_eq!(
+                    after.blocked, from_scratch,
+                    "blocked edges drifted in {after}"
+                );
+                assert_eq!(
+                    after.distances,
+                    Board::compute_distances(&from_scratch),
+                    "distance maps drifted in {after}"
+                );
+
+                // find the move that was played, and take it back.
+                let mut moves = Vec::new();
+                before.generate_moves(|mv| {
+                    let mut child = before;
+                    child.make_move(mv);
+                    if child == after {
+                        moves.push(mv);
+                    }
+                    false
+                });
+                let mut undone = after;
+                undone.unmake_move(moves[0], before.pawns[before.turn().index()]);
+                assert_eq!(undone.blocked, before.blocked);
+                assert_eq!(undone.distances, before.distances);
+            }
+        }
+    }
+
+    #[test]
+    fn distance_maps() {
+        use crate::types::{Colour, Square9x9};
+
+        let square = |s: &str| s.parse::<Square9x9>().unwrap();
+
+        let board = Board::default();
+        assert_eq!(board.distance_to_goal(Colour::White), Some(8));
+        assert_eq!(board.distance_to_goal(Colour::Black), Some(8));
+        assert_eq!(board.distance_map(Colour::White).get(square("a5")), Some(4));
+        assert_eq!(board.distance_map(Colour::Black).get(square("i9")), Some(8));
+        assert_eq!(board.shortest_path_count(Colour::White), 1);
+
+        // both pawns have to detour through the gap on the i-file.
+        let board: Board = "a5c5e5g5 / h5 / e1 e9 / 5 10 / 1".parse().unwrap();
+        assert_eq!(board.distance_to_goal(Colour::White), Some(12));
+        assert_eq!(board.distance_to_goal(Colour::Black), Some(12));
+        assert_eq!(board.distance_map(Colour::White).get(square("h6")), Some(3));
+        assert_eq!(board.distance_map(Colour::White).get(square("h5")), Some(7));
+        let path = board.shortest_path(Colour::White);
+        assert_eq!(path.count(), 13);
+        assert!(path.contains(square("i5")) && path.contains(square("i6")));
+        // any four steps east and three north lead to i4, below the gap.
+        assert_eq!(board.shortest_path_count(Colour::White), 35);
+
+        let board: Board = "a2 / b1 / a1 e9 / 8 8 / 1".parse().unwrap();
+        assert_eq!(board.distance_to_goal(Colour::White), None);
+        assert!(board.shortest_path(Colour::White).is_empty());
+        assert_eq!(board.shortest_path_count(Colour::White), 0);
+        assert_eq!(board.distance_map(Colour::White).get(square("b2")), None);
+        // black's goal row runs through the boxed-in corner.
+        assert_eq!(board.distance_map(Colour::Black).get(square("b2")), Some(1));
+        assert_eq!(board.distance_map(Colour::Black).get(square("c2")), Some(1));
+    }
+
+    #[test]
+    fn distance_maps_match_search() {
+        use crate::types::{Colour, Square9x9};
+        use std::collections::VecDeque;
+
+        // a plain breadth-first search from one square, one step at a time.
+        let distance = |board: &Board, from: Square9x9, colour: Colour| {
+            let mut seen = vec![from];
+            let mut queue = VecDeque::from([(from, 0)]);
+            while let Some((square, distance)) = queue.pop_front() {
+                if colour.goal_row().contains(square) {
+                    return Some(distance);
+                }
+                for direction in super::Direction::ALL {
+                    if let Some(next) = board.step(square, direction) {
+                        if !seen.contains(&next) {
+                            seen.push(next);
+                            queue.push_back((next, distance + 1));
+                        }
+                    }
+                }
+            }
+            None
+        };
+
+        let mut rng = XorShiftRng::new(0xD157);
+        for _ in 0..5 {
+            for board in random_game(&mut rng, 60).into_iter().step_by(5) {
+                for index in 0..81 {
+                    let square = Square9x9::from_index(index).unwrap();
+                    for colour in [Colour::White, Colour::Black] {
+                        assert_eq!(
+                            board.distance_map(colour).get(square),
+                            distance(&board, square, colour),
+                            "distance from {square} for {colour:?} in {board}"
+                        );
+                    }
+                }
+            }
+        }
     }
 }
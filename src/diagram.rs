@@ -0,0 +1,366 @@
+//! Text diagrams of boards, for debugging and logging.
+//!
+//! A diagram draws the grid of squares with both pawns, draws each wall
+//! along the edges it blocks, and notes the walls each player has left
+//! and whose turn it is. A move or a set of squares, such as a shortest
+//! path, can be highlighted.
+
+use std::fmt::Display;
+
+use crate::{
+    board::Board,
+    squareset9x9::SquareSet as SquareSet9x9,
+    types::{Colour, Move, Square8x8, Square9x9, WallOrientation},
+};
+
+/// The characters a diagram is drawn with.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DiagramStyle {
+    /// Plain ASCII, which displays anywhere.
+    #[default]
+    Ascii,
+    /// Box-drawing characters, which look better where they're supported.
+    Unicode,
+}
+
+/// The characters used for each part of a diagram in one style.
+struct Glyphs {
+    empty: char,
+    white: char,
+    black: char,
+    path: char,
+    target: char,
+    horizontal_line: char,
+    vertical_line: char,
+    horizontal_wall: char,
+    vertical_wall: char,
+    horizontal_highlight: char,
+    vertical_highlight: char,
+}
+
+impl DiagramStyle {
+    const fn glyphs(self) -> Glyphs {
+        match self {
+            Self::Ascii => Glyphs {
+                empty: '.',
+                white: 'W',
+                black: 'B',
+                path: '*',
+                target: 'o',
+                horizontal_line: '-',
+                vertical_line: '|',
+                horizontal_wall: '=',
+                vertical_wall: '#',
+                horizontal_highlight: '~',
+                vertical_highlight: ':',
+            },
+            Self::Unicode => Glyphs {
+                empty: ' ',
+                white: '○',
+                black: '●',
+                path: '•',
+                target: '◎',
+                horizontal_line: '─',
+                vertical_line: '│',
+                horizontal_wall: '━',
+                vertical_wall: '┃',
+                horizontal_highlight: '═',
+                vertical_highlight: '║',
+            },
+        }
+    }
+
+    /// Returns the character where grid lines meet at the given boundary
+    /// between ranks and the given edge between files, both counted from 0
+    /// at the south-west corner to 9 at the north-east corner.
+    const fn joint(self, boundary: usize, edge: usize) -> char {
+        match self {
+            Self::Ascii => '+',
+            Self::Unicode => match (boundary, edge) {
+                (9, 0) => '┌',
+                (9, 9) => '┐',
+                (0, 0) => '└',
+                (0, 9) => '┘',
+                (9, _) => '┬',
+                (0, _) => '┴',
+                (_, 0) => '├',
+                (_, 9) => '┤',
+                _ => '┼',
+            },
+        }
+    }
+}
+
+/// A diagram of a board, built with [`Board::diagram`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Diagram<'a> {
+    board: &'a Board,
+    style: DiagramStyle,
+    path: SquareSet9x9,
+    highlighted_move: Option<Move>,
+}
+
+impl<'a> Diagram<'a> {
+    /// Creates a plain ASCII diagram of `board` with nothing highlighted.
+    #[must_use]
+    pub const fn new(board: &'a Board) -> Self {
+        Self {
+            board,
+            style: DiagramStyle::Ascii,
+            path: SquareSet9x9::new(),
+            highlighted_move: None,
+        }
+    }
+
+    /// Sets the characters the diagram is drawn with.
+    #[must_use]
+    pub const fn with_style(mut self, style: DiagramStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Marks the given squares, such as those of a shortest path.
+    #[must_use]
+    pub const fn with_path(mut self, path: SquareSet9x9) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// Marks the square a pawn move goes to, or draws a
+    /// wall move's wall differently to the walls already placed.
+    #[must_use]
+    pub const fn with_move(mut self, mv: Move) -> Self {
+        self.highlighted_move = Some(mv);
+        self
+    }
+
+    /// Returns the character to draw for the wall, if any, with the given
+    /// orientation centred to the north-east of the square at `file` and
+    /// `rank`. Centres off the board have no wall.
+    fn wall(&self, file: usize, rank: usize, orientation: WallOrientation) -> Option<char> {
+        let glyphs = self.style.glyphs();
+        let to_square =
+            Square8x8::from_file_rank(u8::try_from(file).ok()?, u8::try_from(rank).ok()?)?;
+        let highlighted = Move::Wall {
+            to_square,
+            orientation,
+        };
+        match orientation {
+            _ if self.highlighted_move == Some(highlighted) => Some(match orientation {
+                WallOrientation::Horizontal => glyphs.horizontal_highlight,
+                WallOrientation::Vertical => glyphs.vertical_highlight,
+            }),
+            _ if !self.board.has_wall(to_square, orientation) => None,
+            WallOrientation::Horizontal => Some(glyphs.horizontal_wall),
+            WallOrientation::Vertical => Some(glyphs.vertical_wall),
+        }
+    }
+
+    /// Returns the character to draw in the middle of a square.
+    fn square(&self, square: Square9x9) -> char {
+        let glyphs = self.style.glyphs();
+        if square == self.board.pawn(Colour::White) {
+            glyphs.white
+        } else if square == self.board.pawn(Colour::Black) {
+            glyphs.black
+        } else if self.highlighted_move == Some(Move::Pawn { to_square: square }) {
+            glyphs.target
+        } else if self.path.contains(square) {
+            glyphs.path
+        } else {
+            glyphs.empty
+        }
+    }
+
+    /// Draws the grid line at the given boundary, where boundary `b` runs
+    /// between the ranks with indices `b - 1` and `b`.
+    fn boundary(&self, f: &mut std::fmt::Formatter<'_>, boundary: usize) -> std::fmt::Result {
+        let glyphs = self.style.glyphs();
+        let inner = (1..9).contains(&boundary);
+        write!(f, "  ")?;
+        for joint in 0..=9 {
+            let centre = (joint > 0 && inner)
+                .then(|| {
+                    let horizontal =
+                        self.wall(joint - 1, boundary - 1, WallOrientation::Horizontal);
+                    horizontal
+                        .or_else(|| self.wall(joint - 1, boundary - 1, WallOrientation::Vertical))
+                })
+                .flatten();
+            let grid = self.style.joint(boundary, joint);
+            write!(f, "{}", centre.unwrap_or(grid))?;
+            if joint < 9 {
+                // a horizontal wall runs along two squares either side of its centre.
+                let wall = inner
+                    .then(|| {
+                        self.wall(joint, boundary - 1, WallOrientation::Horizontal)
+                            .or_else(|| {
+                                (joint > 0)
+                                    .then(|| {
+                                        self.wall(
+                                            joint - 1,
+                                            boundary - 1,
+                                            WallOrientation::Horizontal,
+                                        )
+                                    })
+                                    .flatten()
+                            })
+                    })
+                    .flatten();
+                let segment = wall.unwrap_or(glyphs.horizontal_line);
+                write!(f, "{segment}{segment}{segment}")?;
+            }
+        }
+        writeln!(f)
+    }
+
+    /// Draws the squares of the rank with the given index.
+    fn rank(&self, f: &mut std::fmt::Formatter<'_>, rank: usize) -> std::fmt::Result {
+        let glyphs = self.style.glyphs();
+        write!(f, "{} ", rank + 1)?;
+        for edge in 0..=9 {
+            // a vertical wall runs along two squares either side of its centre.
+            let wall = (1..9)
+                .contains(&edge)
+                .then(|| {
+                    self.wall(edge - 1, rank, WallOrientation::Vertical)
+                        .or_else(|| {
+                            (rank > 0)
+                                .then(|| self.wall(edge - 1, rank - 1, WallOrientation::Vertical))
+                                .flatten()
+                        })
+                })
+                .flatten();
+            write!(f, "{}", wall.unwrap_or(glyphs.vertical_line))?;
+            if edge < 9 {
+                #[allow(clippy::cast_possible_truncation)]
+                let square = Square9x9::from_file_rank(edge as u8, rank as u8)
+                    .expect("the file and rank are on the board");
+                write!(f, " {} ", self.square(square))?;
+            }
+        }
+        writeln!(f, " {}", rank + 1)
+    }
+}
+
+impl Display for Diagram<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let files = "    a   b   c   d   e   f   g   h   i";
+        writeln!(f, "{files}")?;
+        for rank in (0..9).rev() {
+            self.boundary(f, rank + 1)?;
+            self.rank(f, rank)?;
+        }
+        self.boundary(f, 0)?;
+        writeln!(f, "{files}")?;
+
+        let walls = |colour| self.board.walls_in_pocket(colour);
+        write!(
+            f,
+            "walls: white {}, black {}; ",
+            walls(Colour::White),
+            walls(Colour::Black)
+        )?;
+        match self.board.outcome() {
+            Some(result) if result.winner() == Colour::White => writeln!(f, "white has won"),
+            Some(_) => writeln!(f, "black has won"),
+            None if self.board.turn() == Colour::White => writeln!(f, "white to move"),
+            None => writeln!(f, "black to move"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DiagramStyle;
+    use crate::{board::Board, types::Colour};
+
+    #[test]
+    fn ascii() {
+        let board: Board = "e8c3 / d5 / e7 e9 / 8 9 / 2".parse().unwrap();
+        let expected = "    a   b   c   d   e   f   g   h   i
+  +---+---+---+---+---+---+---+---+---+
+9 | . | . | . | . | B | . | . | . | . | 9
+  +---+---+---+---+=======+---+---+---+
+8 | . | . | . | . | . | . | . | . | . | 8
+  +---+---+---+---+---+---+---+---+---+
+7 | . | . | . | . | W | . | . | . | . | 7
+  +---+---+---+---+---+---+---+---+---+
+6 | . | . | . | . # . | . | . | . | . | 6
+  +---+---+---+---#---+---+---+---+---+
+5 | . | . | . | . # . | . | . | . | . | 5
+  +---+---+---+---+---+---+---+---+---+
+4 | . | . | . | . | . | . | . | . | . | 4
+  +---+---+=======+---+---+---+---+---+
+3 | . | . | . | . | . | . | . | . | . | 3
+  +---+---+---+---+---+---+---+---+---+
+2 | . | . | . | . | . | . | . | . | . | 2
+  +---+---+---+---+---+---+---+---+---+
+1 | . | . | . | . | . | . | . | . | . | 1
+  +---+---+---+---+---+---+---+---+---+
+    a   b   c   d   e   f   g   h   i
+walls: white 8, black 9; black to move
+";
+        assert_eq!(board.diagram().to_string(), expected);
+    }
+
+    #[test]
+    fn unicode() {
+        let board: Board = "e8 /  / e7 e9 / 9 10 / 1".parse().unwrap();
+        let diagram = board
+            .diagram()
+            .with_style(DiagramStyle::Unicode)
+            .to_string();
+        let lines = diagram.lines().collect::<Vec<_>>();
+        assert_eq!(lines[1], "  ┌───┬───┬───┬───┬───┬───┬───┬───┬───┐");
+        assert_eq!(lines[2], "9 │   │   │   │   │ ● │   │   │   │   │ 9");
+        assert_eq!(lines[3], "  ├───┼───┼───┼───┼━━━━━━━┼───┼───┼───┤");
+        assert_eq!(lines[6], "7 │   │   │   │   │ ○ │   │   │   │   │ 7");
+        assert_eq!(lines[19], "  └───┴───┴───┴───┴───┴───┴───┴───┴───┘");
+        assert_eq!(lines[21], "walls: white 9, black 10; white to move");
+    }
+
+    #[test]
+    fn highlights() {
+        let board = Board::default();
+
+        // a pawn move marks the square it goes to.
+        let diagram = board.diagram().with_move("e2".parse().unwrap()).to_string();
+        assert_eq!(
+            diagram.lines().nth(16),
+            Some("2 | . | . | . | . | o | . | . | . | . | 2")
+        );
+
+        // a wall move is drawn in place, differently to placed walls.
+        let diagram = board
+            .diagram()
+            .with_move("a1v".parse().unwrap())
+            .to_string();
+        let lines = diagram.lines().collect::<Vec<_>>();
+        assert_eq!(lines[16], "2 | . : . | . | . | . | . | . | . | . | 2");
+        assert_eq!(lines[17], "  +---:---+---+---+---+---+---+---+---+");
+        assert_eq!(lines[18], "1 | . : . | . | . | W | . | . | . | . | 1");
+        let diagram = board
+            .diagram()
+            .with_move("h8h".parse().unwrap())
+            .to_string();
+        assert_eq!(
+            diagram.lines().nth(3),
+            Some("  +---+---+---+---+---+---+---+~~~~~~~+")
+        );
+
+        // a path marks every square on it but those with pawns.
+        let path = board.shortest_path(Colour::White);
+        let diagram = board.diagram().with_path(path).to_string();
+        let marked = diagram.lines().filter(|line| line.contains('*')).count();
+        assert_eq!(marked, 7);
+        assert!(diagram.contains("5 | . | . | . | . | * | . | . | . | . | 5"));
+    }
+
+    #[test]
+    fn finished_games() {
+        let board: Board = " /  / e9 e5 / 10 10 / 2".parse().unwrap();
+        let diagram = board.diagram().to_string();
+        assert!(diagram.ends_with("walls: white 10, black 10; white has won\n"));
+    }
+}
@@ -0,0 +1,286 @@
+//! Static evaluation of positions.
+//!
+//! A position is scored from the point of view of the side to move, as a
+//! weighted sum of features. The weights live in [`Weights`], which can be
+//! read from and written to a plain text file of `name = value` lines, so
+//! they can be tuned without recompiling.
+
+use std::{fmt::Display, num::ParseIntError, path::Path, str::FromStr};
+
+use crate::{board::Board, search::WIN_BOUND, types::Colour};
+
+/// The weight of each feature of the evaluation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Weights {
+    /// Per step by which the opponent's shortest path is longer than ours.
+    pub path_difference: i32,
+    /// Per wall we have left in hand more than the opponent.
+    pub walls_in_pocket: i32,
+    /// For being the side to move.
+    pub tempo: i32,
+    /// Per doubling of the number of shortest paths we have compared to the
+    /// opponent, as a pawn with many routes to its goal is harder to wall off.
+    pub path_count: i32,
+}
+
+impl Weights {
+    /// The names of the weights, as written in a weights file.
+    pub const NAMES: [&'static str; 4] =
+        ["path_difference", "walls_in_pocket", "tempo", "path_count"];
+
+    /// Returns the weight with the given name.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<i32> {
+        match name {
+            "path_difference" => Some(self.path_difference),
+            "walls_in_pocket" => Some(self.walls_in_pocket),
+            "tempo" => Some(self.tempo),
+            "path_count" => Some(self.path_count),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the weight with the given name.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut i32> {
+        match name {
+            "path_difference" => Some(&mut self.path_difference),
+            "walls_in_pocket" => Some(&mut self.walls_in_pocket),
+            "tempo" => Some(&mut self.tempo),
+            "path_count" => Some(&mut self.path_count),
+            _ => None,
+        }
+    }
+
+    /// Reads weights from the file at `path`. See [`Weights::from_str`] for the format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or isn't a valid weights file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, WeightsLoadError> {
+        let text = std::fs::read_to_string(path).map_err(WeightsLoadError::Io)?;
+        text.parse().map_err(WeightsLoadError::Parse)
+    }
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            path_difference: 100,
+            walls_in_pocket: 20,
+            tempo: 10,
+            path_count: 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WeightsParseError {
+    MissingEquals { line: usize },
+    UnknownWeight { line: usize, name: String },
+    DuplicateWeight { line: usize, name: String },
+    ValueParseError { line: usize, error: ParseIntError },
+}
+
+#[derive(Debug)]
+pub enum WeightsLoadError {
+    Io(std::io::Error),
+    Parse(WeightsParseError),
+}
+
+impl FromStr for Weights {
+    type Err = WeightsParseError;
+
+    /// Parses weights from lines of the form `name = value`. Blank lines and
+    /// lines starting with `#` are ignored, and any weight not given keeps
+    /// its default value.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = Self::default();
+        let mut seen = Vec::new();
+        for (index, text) in s.lines().enumerate() {
+            let line = index + 1;
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            let Some((name, value)) = text.split_once('=') else {
+                return Err(WeightsParseError::MissingEquals { line });
+            };
+            let name = name.trim();
+            let Some(weight) = weights.get_mut(name) else {
+                return Err(WeightsParseError::UnknownWeight {
+                    line,
+                    name: name.to_string(),
+                });
+            };
+            *weight = value
+                .trim()
+                .parse()
+                .map_err(|error| WeightsParseError::ValueParseError { line, error })?;
+            if seen.contains(&name) {
+                return Err(WeightsParseError::DuplicateWeight {
+                    line,
+                    name: name.to_string(),
+                });
+            }
+            seen.push(name);
+        }
+        Ok(weights)
+    }
+}
+
+impl Display for Weights {
+    /// Writes the weights in the format read by [`Weights::from_str`].
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for name in Self::NAMES {
+            let value = self.get(name).expect("every name has a weight");
+            writeln!(f, "{name} = {value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Scores `board` from the point of view of the side to move.
+/// Positive scores are good for the side to move.
+///
+/// However large the weights, the score stays short of those the search
+/// uses for forced wins and losses, so it is never mistaken for one.
+#[must_use]
+pub fn evaluate(board: &Board, weights: &Weights) -> i32 {
+    let us = board.turn();
+    let them = us.opponent();
+    let distance = |colour| i64::from(board.distance_to_goal(colour).unwrap_or(u8::MAX));
+    let walls = |colour| i64::from(board.walls_in_pocket(colour));
+    let weight = i64::from;
+    let score = weight(weights.path_difference) * (distance(them) - distance(us))
+        + weight(weights.walls_in_pocket) * (walls(us) - walls(them))
+        + weight(weights.tempo)
+        + weight(weights.path_count) * (path_count_bits(board, us) - path_count_bits(board, them));
+    let bound = i64::from(WIN_BOUND - 1);
+    #[allow(clippy::cast_possible_truncation)]
+    let score = score.clamp(-bound, bound) as i32;
+    score
+}
+
+/// Returns the number of bits in the count of the given colour's shortest
+/// paths, which grows by one each time the count doubles.
+fn path_count_bits(board: &Board, colour: Colour) -> i64 {
+    let count = board.shortest_path_count(colour);
+    i64::from(u64::BITS - count.leading_zeros())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, Weights, WeightsParseError};
+    use crate::{
+        board::Board,
+        search::{plies_to_win, Limits, Searcher, WIN_BOUND},
+    };
+
+    #[test]
+    fn symmetric_positions() {
+        let weights = Weights::default();
+        // each side sees the same position, so only the tempo differs.
+        let board = Board::default();
+        assert_eq!(evaluate(&board, &weights), weights.tempo);
+        let board: Board = " /  / e4 e6 / 10 10 / 2".parse().unwrap();
+        assert_eq!(evaluate(&board, &weights), weights.tempo);
+    }
+
+    #[test]
+    fn features() {
+        let weights = Weights {
+            path_difference: 1000,
+            walls_in_pocket: 100,
+            tempo: 10,
+            path_count: 1,
+        };
+
+        // white is two steps ahead and a wall behind.
+        let board: Board = " /  / e3 e9 / 9 10 / 1".parse().unwrap();
+        assert_eq!(evaluate(&board, &weights), 2000 - 100 + 10);
+        let board: Board = " /  / e3 e9 / 9 10 / 2".parse().unwrap();
+        assert_eq!(evaluate(&board, &weights), -2000 + 100 + 10);
+
+        // a wall above e8 leaves white two ways round it, through d7 or d8,
+        // while black has to step aside to d9 and then go straight down.
+        let board: Board = "e8 /  / e7 e9 / 9 10 / 1".parse().unwrap();
+        assert_eq!(evaluate(&board, &weights), 6000 - 100 + 10 + 1);
+    }
+
+    #[test]
+    fn huge_weights() {
+        let weights = Weights {
+            path_difference: 20_000,
+            ..Weights::default()
+        };
+        // white is three steps ahead, which would score 60,000 unbounded.
+        let board: Board = " /  / e5 e8 / 10 10 / 1".parse().unwrap();
+        assert_eq!(evaluate(&board, &weights), WIN_BOUND - 1);
+        let board: Board = " /  / e5 e8 / 10 10 / 2".parse().unwrap();
+        assert_eq!(evaluate(&board, &weights), -(WIN_BOUND - 1));
+        let weights = Weights {
+            path_difference: i32::MAX,
+            tempo: i32::MAX,
+            ..Weights::default()
+        };
+        assert_eq!(evaluate(&board, &weights), -(WIN_BOUND - 1));
+
+        // so the search never takes the bounded score for a forced result.
+        let mut searcher = Searcher::new(1);
+        searcher.set_weights(Weights {
+            path_difference: 20_000,
+            ..Weights::default()
+        });
+        let limits = Limits {
+            depth: Some(3),
+            ..Limits::default()
+        };
+        let result = searcher.search(&board, &limits);
+        assert_eq!(plies_to_win(result.score), None);
+    }
+
+    #[test]
+    fn weights_files() {
+        let weights = Weights {
+            path_difference: 90,
+            walls_in_pocket: -3,
+            tempo: 0,
+            path_count: 12,
+        };
+        assert_eq!(weights.to_string().parse(), Ok(weights));
+
+        let text = "# a partial set of weights\n\n  tempo = 25  \npath_count=4\n";
+        let parsed: Weights = text.parse().unwrap();
+        assert_eq!(
+            parsed,
+            Weights {
+                tempo: 25,
+                path_count: 4,
+                ..Weights::default()
+            }
+        );
+
+        assert_eq!(
+            "tempo 25".parse::<Weights>(),
+            Err(WeightsParseError::MissingEquals { line: 1 })
+        );
+        assert_eq!(
+            "\nmobility = 3".parse::<Weights>(),
+            Err(WeightsParseError::UnknownWeight {
+                line: 2,
+                name: "mobility".to_string()
+            })
+        );
+        assert_eq!(
+            "tempo = 1\ntempo = 2".parse::<Weights>(),
+            Err(WeightsParseError::DuplicateWeight {
+                line: 2,
+                name: "tempo".to_string()
+            })
+        );
+        assert!(matches!(
+            "tempo = ten".parse::<Weights>(),
+            Err(WeightsParseError::ValueParseError { line: 1, .. })
+        ));
+    }
+}
@@ -2,9 +2,26 @@
 //! A library for the game "Quoridor".
 
 pub mod board;
-mod types;
+pub mod diagram;
+pub mod svg;
+pub mod types;
 mod squareset;
+pub mod squareset9x9;
+mod zobrist;
+pub mod movelist;
+pub mod movepick;
+pub mod notation;
 pub mod perft;
+pub mod position;
+pub mod search;
+#[cfg(feature = "serde")]
+mod serialization;
+pub mod eval;
+pub mod protocol;
+pub mod record;
+pub mod mcts;
+pub mod tt;
+pub(crate) mod rng;
 
 #[cfg(test)]
 mod tests {
@@ -1,10 +1,95 @@
-use quorigen::{board::Board, perft};
+use quorigen::{board::Board, perft, protocol::Engine, tt::TranspositionTable};
+
+/// The size of the perft cache, if not given on the command line.
+const DEFAULT_HASH_MEGABYTES: usize = 64;
+
+/// The positions and depths timed by the bench mode: the start position,
+/// a board with walls to route around and left to place, and two pawn races.
+const BENCH_POSITIONS: [(&str, u8); 4] = [
+    (" /  / e1 e9 / 10 10 / 1", 4),
+    ("a5c5e5g5 / h5 / e1 e9 / 5 10 / 1", 3),
+    (" /  / e2 e8 / 0 0 / 1", 14),
+    ("c3e3g3c7e7g7 / b5d5f5h5 / e4 e6 / 0 0 / 1", 15),
+];
 
 fn main() {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        None => Engine::new(std::io::stdout()).run(std::io::stdin().lock()),
+        Some("perft") => run_perft(args),
+        Some("bench") => run_bench(),
+        Some("suite") => match args.next() {
+            Some(path) => run_suite(&path),
+            None => usage(),
+        },
+        Some(mode) => {
+            eprintln!("unknown mode {mode}");
+            usage();
+        }
+    }
+}
+
+/// Prints how to run the program and exits with an error.
+fn usage() -> ! {
+    eprintln!("usage: quorigen [perft [megabytes] [threads] | suite <path> | bench]");
+    std::process::exit(2);
+}
+
+fn run_perft(mut args: impl Iterator<Item = String>) {
+    let megabytes = args.next().map_or(DEFAULT_HASH_MEGABYTES, |arg| {
+        arg.parse().expect("hash size must be a number of megabytes")
+    });
+    let threads = args.next().map_or_else(
+        || std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get),
+        |arg| arg.parse().expect("thread count must be a number"),
+    );
+    let table = TranspositionTable::new(megabytes);
     let start = std::time::Instant::now();
-    let mut map = std::collections::HashMap::new();
     for depth in 0.. {
-        let count = perft::perft_cached(Board::default(), depth, &mut map);
+        let count = perft::perft_parallel(Board::default(), depth, threads, Some(&table));
         println!("perft({}) = {} in {:.3}s", depth, count, start.elapsed().as_secs_f64());
     }
-}
\ No newline at end of file
+}
+
+/// Times serial perft on each of the bench positions, without a cache,
+/// so that changes to move generation can be compared.
+#[allow(clippy::cast_precision_loss)]
+fn run_bench() {
+    let mut total_nodes = 0;
+    let mut total_seconds = 0.0;
+    for (fen, depth) in BENCH_POSITIONS {
+        let board: Board = fen.parse().expect("bench positions are valid");
+        let start = std::time::Instant::now();
+        let nodes = perft::perft(board, depth);
+        let seconds = start.elapsed().as_secs_f64();
+        println!(
+            "{fen:<42} depth {depth:>2}: {nodes:>10} nodes in {seconds:.3}s, {:.1}M nps",
+            nodes as f64 / seconds / 1e6
+        );
+        total_nodes += nodes;
+        total_seconds += seconds;
+    }
+    println!(
+        "total: {total_nodes} nodes in {total_seconds:.3}s, {:.1}M nps",
+        total_nodes as f64 / total_seconds / 1e6
+    );
+}
+
+fn run_suite(path: &str) {
+    let text = std::fs::read_to_string(path).expect("failed to read perft suite");
+    let entries = perft::parse_suite(&text).expect("malformed perft suite");
+    let start = std::time::Instant::now();
+    let mismatches = perft::run_suite(&entries);
+    for mismatch in &mismatches {
+        println!("{mismatch}");
+    }
+    println!(
+        "{} of {} positions passed in {:.3}s",
+        entries.len() - mismatches.len(),
+        entries.len(),
+        start.elapsed().as_secs_f64()
+    );
+    if !mismatches.is_empty() {
+        std::process::exit(1);
+    }
+}
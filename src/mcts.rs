@@ -0,0 +1,493 @@
+//! Monte Carlo tree search.
+//!
+//! The tree is grown one node per playout, choosing children by UCT, and
+//! each new node is scored by playing the game out with a [`RolloutPolicy`].
+//! The tree is kept between moves: after [`Mcts::advance`], the subtree
+//! below the move played becomes the new tree.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use crate::{
+    board::Board,
+    rng::XorShiftRng,
+    search::Limits,
+    types::{Colour, Move, Square8x8, WallOrientation},
+};
+
+/// The number of plies after which a playout is stopped, and the
+/// player closer to their goal row is taken to have won.
+pub const MAX_ROLLOUT_PLIES: usize = 256;
+
+/// A way of choosing moves during playouts.
+pub trait RolloutPolicy {
+    /// Chooses a legal move in `board`, or returns `None` if there are none.
+    /// `board` is never a finished game.
+    fn choose(&mut self, board: &Board, rng: &mut XorShiftRng) -> Option<Move>;
+}
+
+/// Plays uniformly random legal moves. Most of these are walls,
+/// so playouts are long and mostly decided by the walls running out.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RandomPolicy;
+
+impl RolloutPolicy for RandomPolicy {
+    fn choose(&mut self, board: &Board, rng: &mut XorShiftRng) -> Option<Move> {
+        let moves = board.legal_moves();
+        if moves.is_empty() {
+            return None;
+        }
+        Some(moves[rng.next_below(moves.len())])
+    }
+}
+
+/// Moves the pawn along a shortest path to the goal row, choosing at
+/// random between equally short paths, and never places walls.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GreedyPolicy;
+
+impl RolloutPolicy for GreedyPolicy {
+    fn choose(&mut self, board: &Board, rng: &mut XorShiftRng) -> Option<Move> {
+        let distances = board.distance_map(board.turn());
+        let mut best = None;
+        let mut best_distance = u8::MAX;
+        let mut ties = 0;
+        board.generate_pawn_moves(|mv| {
+            let Move::Pawn { to_square } = mv else {
+                return false;
+            };
+            let distance = distances.get(to_square).unwrap_or(u8::MAX);
+            if distance < best_distance {
+                (best, best_distance, ties) = (Some(mv), distance, 1);
+            } else if distance == best_distance {
+                // reservoir sampling, so each tie is equally likely.
+                ties += 1;
+                if rng.next_below(ties) == 0 {
+                    best = Some(mv);
+                }
+            }
+            false
+        });
+        // a pawn with no steps may still be able to place a wall.
+        best.or_else(|| RandomPolicy.choose(board, rng))
+    }
+}
+
+/// Places a random wall with probability `epsilon`, if there are walls left,
+/// and otherwise plays as [`GreedyPolicy`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EpsilonGreedyPolicy {
+    /// The probability of placing a wall, in `0.0..=1.0`.
+    pub epsilon: f64,
+}
+
+impl EpsilonGreedyPolicy {
+    /// The number of random wall placements to try before giving up and
+    /// moving the pawn, as most of them are illegal late in the game.
+    const WALL_ATTEMPTS: usize = 8;
+}
+
+impl Default for EpsilonGreedyPolicy {
+    fn default() -> Self {
+        Self { epsilon: 0.25 }
+    }
+}
+
+impl RolloutPolicy for EpsilonGreedyPolicy {
+    fn choose(&mut self, board: &Board, rng: &mut XorShiftRng) -> Option<Move> {
+        if board.walls_in_pocket(board.turn()) > 0 && rng.next_f64() < self.epsilon {
+            for _ in 0..Self::WALL_ATTEMPTS {
+                let index = rng.next_below(64 * 2);
+                #[allow(clippy::cast_possible_truncation)]
+                let to_square = Square8x8::from_index((index / 2) as u8).expect("index < 64");
+                let orientation = if index.is_multiple_of(2) {
+                    WallOrientation::Horizontal
+                } else {
+                    WallOrientation::Vertical
+                };
+                if board.is_legal_wall(to_square, orientation) {
+                    return Some(Move::Wall {
+                        to_square,
+                        orientation,
+                    });
+                }
+            }
+        }
+        GreedyPolicy.choose(board, rng)
+    }
+}
+
+/// The statistics gathered for a move from the root.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MoveStats {
+    /// The move.
+    pub mv: Move,
+    /// The number of playouts that began with this move.
+    pub visits: u32,
+    /// The fraction of those playouts won by the side to move at the root,
+    /// counting unfinished games as half a win.
+    pub win_rate: f64,
+}
+
+/// A node of the search tree.
+#[derive(Clone, Debug, Default)]
+struct Node {
+    /// The move that leads to this node, which is `None` only at the root.
+    mv: Option<Move>,
+    /// The indices of the children expanded so far, in move generation order.
+    children: Vec<u32>,
+    /// The number of legal moves in this node's position.
+    legal_moves: u8,
+    visits: u32,
+    /// The total score of the playouts through this node, from the
+    /// point of view of the player who made the move leading to it.
+    score: f64,
+}
+
+impl Node {
+    fn new(mv: Option<Move>, board: &Board) -> Self {
+        #[allow(clippy::cast_possible_truncation)]
+        let legal_moves = board.legal_moves().len() as u8;
+        Self {
+            mv,
+            children: Vec::new(),
+            legal_moves,
+            visits: 0,
+            score: 0.0,
+        }
+    }
+
+    fn is_fully_expanded(&self) -> bool {
+        self.children.len() == usize::from(self.legal_moves)
+    }
+}
+
+/// A Monte Carlo tree search, rooted at the position to play from.
+#[derive(Debug)]
+pub struct Mcts<P> {
+    board: Board,
+    /// The tree, stored as an arena with the root at index zero.
+    nodes: Vec<Node>,
+    policy: P,
+    rng: XorShiftRng,
+    exploration: f64,
+    stop: Arc<AtomicBool>,
+}
+
+impl<P: RolloutPolicy> Mcts<P> {
+    /// The default exploration constant for UCT, the square root of two.
+    pub const DEFAULT_EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+    /// Creates a search of `board`, with playouts using `policy` and
+    /// randomness seeded by `seed`.
+    #[must_use]
+    pub fn new(board: Board, policy: P, seed: u64) -> Self {
+        Self {
+            board,
+            nodes: vec![Node::new(None, &board)],
+            policy,
+            rng: XorShiftRng::new(seed),
+            exploration: Self::DEFAULT_EXPLORATION,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Sets the exploration constant for UCT. Larger values
+    /// spread playouts more evenly between moves.
+    #[must_use]
+    pub const fn with_exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    /// Returns the position at the root of the tree.
+    #[must_use]
+    pub const fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Returns a flag that stops the search in progress when set.
+    /// The flag is cleared at the start of every search.
+    #[must_use]
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    /// Returns the total number of playouts through the root,
+    /// including those kept from before the last move.
+    #[must_use]
+    pub fn playouts(&self) -> u32 {
+        self.nodes[0].visits
+    }
+
+    /// Runs playouts until a limit is reached. `limits.nodes` is the number
+    /// of playouts to run, and `limits.depth` is ignored. With no limits,
+    /// the search runs until told to stop by [`Self::stop_flag`].
+    /// Returns the best move found.
+    pub fn search(&mut self, limits: &Limits) -> Option<Move> {
+        self.stop.store(false, Ordering::Relaxed);
+        let start = Instant::now();
+        let mut playouts = 0;
+        while self.nodes[0].legal_moves > 0 {
+            if self.stop.load(Ordering::Relaxed)
+                || limits.nodes.is_some_and(|nodes| playouts >= nodes)
+                || limits.time.is_some_and(|time| start.elapsed() >= time)
+            {
+                break;
+            }
+            self.playout();
+            playouts += 1;
+        }
+        self.best_move()
+    }
+
+    /// Returns the move from the root with the most playouts.
+    #[must_use]
+    pub fn best_move(&self) -> Option<Move> {
+        self.root_stats().first().map(|stats| stats.mv)
+    }
+
+    /// Returns the statistics for every move from the root that has been
+    /// tried, with the most visited first.
+    #[must_use]
+    pub fn root_stats(&self) -> Vec<MoveStats> {
+        let mut stats = self.nodes[0]
+            .children
+            .iter()
+            .filter_map(|&child| {
+                let node = &self.nodes[child as usize];
+                Some(MoveStats {
+                    mv: node.mv?,
+                    visits: node.visits,
+                    win_rate: node.score / f64::from(node.visits.max(1)),
+                })
+            })
+            .collect::<Vec<_>>();
+        stats.sort_by_key(|stats| std::cmp::Reverse(stats.visits));
+        stats
+    }
+
+    /// Plays `mv` at the root, keeping the subtree below it.
+    /// If `mv` hasn't been tried yet, the tree starts again from scratch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mv` is illegal.
+    pub fn advance(&mut self, mv: Move) {
+        assert!(
+            self.board.is_legal(mv),
+            "cannot advance by the illegal move {mv}"
+        );
+        self.board.make_move(mv);
+        let child = self.nodes[0]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| self.nodes[child as usize].mv == Some(mv));
+        let Some(child) = child else {
+            self.nodes = vec![Node::new(None, &self.board)];
+            return;
+        };
+
+        // copy the subtree into a new arena, renumbering as we go.
+        let mut old = std::mem::take(&mut self.nodes);
+        let mut root = std::mem::take(&mut old[child as usize]);
+        root.mv = None;
+        self.nodes.push(root);
+        let mut next = 0;
+        while next < self.nodes.len() {
+            let children = std::mem::take(&mut self.nodes[next].children);
+            let mut renumbered = Vec::with_capacity(children.len());
+            for child in children {
+                #[allow(clippy::cast_possible_truncation)]
+                renumbered.push(self.nodes.len() as u32);
+                self.nodes.push(std::mem::take(&mut old[child as usize]));
+            }
+            self.nodes[next].children = renumbered;
+            next += 1;
+        }
+    }
+
+    /// Descends the tree by UCT, expands one new node,
+    /// plays the game out from it, and records the result.
+    fn playout(&mut self) {
+        let mut board = self.board;
+        // each node on the path, with the player who moved into it.
+        let mut path = vec![(0, board.turn().opponent())];
+        loop {
+            let (index, _) = *path.last().expect("the path starts at the root");
+            let node = &self.nodes[index];
+            if node.legal_moves == 0 {
+                break;
+            }
+            let mover = board.turn();
+            if !node.is_fully_expanded() {
+                let mv = board.legal_moves()[node.children.len()];
+                board.make_move(mv);
+                #[allow(clippy::cast_possible_truncation)]
+                let child = self.nodes.len() as u32;
+                self.nodes[index].children.push(child);
+                self.nodes.push(Node::new(Some(mv), &board));
+                path.push((child as usize, mover));
+                break;
+            }
+            let child = self.select_child(index);
+            board.make_move(self.nodes[child].mv.expect("only the root has no move"));
+            path.push((child, mover));
+        }
+
+        let winner = self.rollout(board);
+        for (index, mover) in path {
+            let node = &mut self.nodes[index];
+            node.visits += 1;
+            node.score += match winner {
+                Some(winner) if winner == mover => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            };
+        }
+    }
+
+    /// Returns the child of the given fully-expanded node with the highest UCT score.
+    fn select_child(&self, index: usize) -> usize {
+        let node = &self.nodes[index];
+        let log_visits = f64::from(node.visits.max(1)).ln();
+        let uct = |child: u32| {
+            let child = &self.nodes[child as usize];
+            let visits = f64::from(child.visits.max(1));
+            self.exploration
+                .mul_add((log_visits / visits).sqrt(), child.score / visits)
+        };
+        let best = node
+            .children
+            .iter()
+            .copied()
+            .max_by(|&a, &b| uct(a).total_cmp(&uct(b)))
+            .expect("a fully-expanded node with legal moves has children");
+        best as usize
+    }
+
+    /// Plays the game out from `board` with the rollout policy,
+    /// returning the winner, or `None` if neither player could be
+    /// said to have won.
+    fn rollout(&mut self, mut board: Board) -> Option<Colour> {
+        for _ in 0..MAX_ROLLOUT_PLIES {
+            if let Some(result) = board.outcome() {
+                return Some(result.winner());
+            }
+            let mv = self.policy.choose(&board, &mut self.rng)?;
+            board.make_move(mv);
+        }
+        if let Some(result) = board.outcome() {
+            return Some(result.winner());
+        }
+
+        // adjudicate by who would reach their goal row first, were the
+        // rest of the game a race. the side to move wins ties.
+        let us = board.turn();
+        let distance = |colour| board.distance_to_goal(colour).unwrap_or(u8::MAX);
+        match distance(us).cmp(&distance(us.opponent())) {
+            std::cmp::Ordering::Greater => Some(us.opponent()),
+            _ => Some(us),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EpsilonGreedyPolicy, GreedyPolicy, Mcts, RandomPolicy, RolloutPolicy};
+    use crate::{board::Board, rng::XorShiftRng, search::Limits, types::Move};
+
+    fn playouts(n: u64) -> Limits {
+        Limits {
+            nodes: Some(n),
+            ..Limits::default()
+        }
+    }
+
+    #[test]
+    fn policies_play_legal_moves() {
+        let mut rng = XorShiftRng::new(7);
+        let policies: [&mut dyn RolloutPolicy; 3] = [
+            &mut RandomPolicy,
+            &mut GreedyPolicy,
+            &mut EpsilonGreedyPolicy { epsilon: 0.5 },
+        ];
+        for policy in policies {
+            let mut board = Board::default();
+            for _ in 0..200 {
+                if board.outcome().is_some() {
+                    break;
+                }
+                let mv = policy.choose(&board, &mut rng).unwrap();
+                assert!(board.is_legal(mv), "{mv} is illegal in {board}");
+                board.make_move(mv);
+            }
+        }
+
+        // the greedy policy only ever steps closer to the goal row.
+        let board = Board::default();
+        for _ in 0..10 {
+            assert_eq!(
+                GreedyPolicy.choose(&board, &mut rng),
+                Some("e2".parse().unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn finds_immediate_win() {
+        let board: Board = " /  / e8 a9 / 0 0 / 1".parse().unwrap();
+        let mut mcts = Mcts::new(board, GreedyPolicy, 1);
+        assert_eq!(mcts.search(&playouts(200)), Some("e9".parse().unwrap()));
+        let stats = mcts.root_stats();
+        assert_eq!(stats[0].mv, "e9".parse::<Move>().unwrap());
+        assert!((stats[0].win_rate - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn statistics_cover_root_moves() {
+        let mut mcts = Mcts::new(Board::default(), EpsilonGreedyPolicy::default(), 3);
+        mcts.search(&playouts(500));
+        assert_eq!(mcts.playouts(), 500);
+        let stats = mcts.root_stats();
+        // every root move is tried before any is tried twice.
+        assert_eq!(stats.len(), 131);
+        assert_eq!(stats.iter().map(|stats| stats.visits).sum::<u32>(), 500);
+        assert!(stats
+            .windows(2)
+            .all(|pair| pair[0].visits >= pair[1].visits));
+        assert!(stats
+            .iter()
+            .all(|stats| (0.0..=1.0).contains(&stats.win_rate)));
+    }
+
+    #[test]
+    fn tree_reuse() {
+        let mut mcts = Mcts::new(Board::default(), GreedyPolicy, 5).with_exploration(0.5);
+        let best = mcts.search(&playouts(2000)).unwrap();
+        let kept = mcts.root_stats()[0].visits;
+        mcts.advance(best);
+        assert_eq!(mcts.playouts(), kept);
+        let mut board = Board::default();
+        board.make_move(best);
+        assert_eq!(mcts.board(), &board);
+        for stats in mcts.root_stats() {
+            assert!(board.is_legal(stats.mv));
+        }
+
+        // an untried move throws the tree away. moves are tried in
+        // generation order, so the last wall is the last to be tried.
+        let mut mcts = Mcts::new(Board::default(), GreedyPolicy, 5);
+        mcts.search(&playouts(10));
+        mcts.advance("h8v".parse().unwrap());
+        assert_eq!(mcts.playouts(), 0);
+        mcts.search(&playouts(10));
+        assert_eq!(mcts.playouts(), 10);
+    }
+}
@@ -0,0 +1,224 @@
+//! A fixed-capacity list of moves that lives on the stack.
+
+use std::ops::{Deref, DerefMut};
+
+use crate::types::{Move, Square9x9};
+
+/// The most legal moves there can be in any position.
+///
+/// A pawn has at most five moves: three steps, and a diagonal jump to either side of an opponent
+/// blocking the fourth direction. There are 64 places for each orientation of wall.
+pub const MAX_MOVES: usize = 5 + 64 * 2;
+
+/// A list of at most [`MAX_MOVES`] moves.
+/// Dereferences to a slice, so the usual slice methods are all available.
+#[derive(Clone, Copy)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    /// Creates an empty list.
+    #[must_use]
+    pub const fn new() -> Self {
+        // SAFETY: zero is the index of a1.
+        const FILLER: Move = Move::Pawn {
+            to_square: unsafe { Square9x9::from_index_unchecked(0) },
+        };
+        Self {
+            moves: [FILLER; MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    /// Appends a move to the list.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the list is already full.
+    pub fn push(&mut self, mv: Move) {
+        assert!(self.len < MAX_MOVES, "move list is full");
+        self.moves[self.len] = mv;
+        self.len += 1;
+    }
+
+    /// Removes every move from the list.
+    pub const fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Determines whether the list contains the given move.
+    #[must_use]
+    pub fn contains(&self, mv: Move) -> bool {
+        self[..].contains(&mv)
+    }
+
+    /// Sorts the list so that the moves with the highest scores come first.
+    /// Moves with equal scores keep their order. Each move is only scored
+    /// once, so `score` may be expensive.
+    pub fn sort_by_score(&mut self, mut score: impl FnMut(Move) -> i32) {
+        let mut scores = [0; MAX_MOVES];
+        for (score_slot, &mv) in scores.iter_mut().zip(self.iter()) {
+            *score_slot = score(mv);
+        }
+        // insertion sort, as the lists are short and often nearly sorted already.
+        for i in 1..self.len {
+            let (mv, mv_score) = (self.moves[i], scores[i]);
+            let mut j = i;
+            while j > 0 && scores[j - 1] < mv_score {
+                self.moves[j] = self.moves[j - 1];
+                scores[j] = scores[j - 1];
+                j -= 1;
+            }
+            self.moves[j] = mv;
+            scores[j] = mv_score;
+        }
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for MoveList {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for MoveList {}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &Self::Target {
+        &self.moves[..self.len]
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = MoveListIntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        MoveListIntoIter {
+            list: self,
+            index: 0,
+        }
+    }
+}
+
+/// An iterator over the moves of a [`MoveList`], by value.
+#[derive(Clone, Debug)]
+pub struct MoveListIntoIter {
+    list: MoveList,
+    index: usize,
+}
+
+impl Iterator for MoveListIntoIter {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mv = self.list.get(self.index).copied();
+        self.index += usize::from(mv.is_some());
+        mv
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.list.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for MoveListIntoIter {}
+
+impl Extend<Move> for MoveList {
+    fn extend<T: IntoIterator<Item = Move>>(&mut self, iter: T) {
+        for mv in iter {
+            self.push(mv);
+        }
+    }
+}
+
+impl FromIterator<Move> for MoveList {
+    fn from_iter<T: IntoIterator<Item = Move>>(iter: T) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MoveList, MAX_MOVES};
+    use crate::{board::Board, types::Move};
+
+    #[test]
+    fn push_and_contains() {
+        let mut list = MoveList::new();
+        assert!(list.is_empty());
+        let e2 = "e2".parse().unwrap();
+        list.push(e2);
+        list.push("a1h".parse().unwrap());
+        assert_eq!(list.len(), 2);
+        assert!(list.contains(e2));
+        assert!(!list.contains("e3".parse().unwrap()));
+        assert_eq!(list.into_iter().len(), 2);
+        list.clear();
+        assert!(!list.contains(e2));
+    }
+
+    #[test]
+    fn sort_by_score_is_stable() {
+        let mut list = ["a1h", "b1h", "c1h", "d1h", "e2"]
+            .into_iter()
+            .map(|mv| mv.parse::<Move>().unwrap())
+            .collect::<MoveList>();
+        // pawn moves first, then walls by file, with ties kept in order.
+        list.sort_by_score(|mv| match mv {
+            Move::Pawn { .. } => 100,
+            Move::Wall { to_square, .. } => i32::from(to_square.file()) / 2,
+        });
+        let sorted = list.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(sorted, ["e2", "c1h", "d1h", "a1h", "b1h"]);
+    }
+
+    #[test]
+    fn legal_moves_fit() {
+        // three steps and a straight jump, and every wall is still available.
+        let board: Board = " /  / e5 e6 / 10 10 / 1".parse().unwrap();
+        let moves = board.legal_moves();
+        assert_eq!(moves.len(), 3 + 1 + 128);
+
+        // with a wall behind black, white can jump diagonally either way.
+        let board: Board = "e6 /  / e5 e6 / 10 10 / 1".parse().unwrap();
+        let moves = board.legal_moves();
+        assert!(moves.len() <= MAX_MOVES);
+        assert!(moves.contains("d6".parse().unwrap()));
+        assert!(moves.contains("f6".parse().unwrap()));
+    }
+}
@@ -0,0 +1,400 @@
+//! Staged move generation for search.
+//!
+//! Rather than generating every move up front, a [`MovePicker`] yields moves
+//! in batches, in the order they're most likely to be good, and only generates
+//! each batch once the one before it has run out. A search that cuts off after
+//! the first few moves never pays for the legality checks of the walls it
+//! didn't try.
+
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use crate::{
+    board::Board,
+    movelist::MoveList,
+    squareset::SquareSet,
+    types::{Colour, Move},
+};
+
+/// The batches of moves yielded by a [`MovePicker`], in the order they're yielded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    /// The move suggested by the caller, typically from the transposition table.
+    HashMove,
+    /// Pawn moves, nearest to the goal row first.
+    PawnMoves,
+    /// Walls that caused cutoffs in sibling positions.
+    Killers,
+    /// Walls touching either pawn's shortest path to its goal row,
+    /// best history score first.
+    RelevantWalls,
+    /// Every other wall, best history score first.
+    OtherWalls,
+    /// No moves are left.
+    Done,
+}
+
+/// Scores for each move by how often it has caused a cutoff in the search,
+/// kept separately for each side to move. Used to order the walls.
+///
+/// The scores may be updated while move pickers are reading them,
+/// as happens when a search recurses into the moves of a picker.
+#[derive(Debug)]
+pub struct History {
+    /// Indexed by colour and then by packed move.
+    scores: [[AtomicI32; 1 << 9]; 2],
+}
+
+impl History {
+    /// The bound on the magnitude of any score.
+    pub const MAX: i32 = 1 << 14;
+
+    /// Creates a table in which every move scores zero.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            scores: std::array::from_fn(|_| std::array::from_fn(|_| AtomicI32::new(0))),
+        }
+    }
+
+    /// Returns the score of `mv` when played by `colour`.
+    #[must_use]
+    pub fn get(&self, colour: Colour, mv: Move) -> i32 {
+        self.scores[colour.index()][usize::from(mv.to_u16())].load(Ordering::Relaxed)
+    }
+
+    /// Adds `bonus`, which may be negative, to the score of `mv` when played by
+    /// `colour`. Scores near the bound move less, so they never exceed it.
+    pub fn update(&self, colour: Colour, mv: Move, bonus: i32) {
+        let bonus = bonus.clamp(-Self::MAX, Self::MAX);
+        let slot = &self.scores[colour.index()][usize::from(mv.to_u16())];
+        let score = slot.load(Ordering::Relaxed);
+        slot.store(
+            score + bonus - score * bonus.abs() / Self::MAX,
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Halves every score, so that older results count for less.
+    pub fn age(&self) {
+        for slot in self.scores.iter().flatten() {
+            slot.store(slot.load(Ordering::Relaxed) / 2, Ordering::Relaxed);
+        }
+    }
+
+    /// Resets every score to zero.
+    pub fn clear(&self) {
+        for slot in self.scores.iter().flatten() {
+            slot.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Yields the legal moves in a position one stage at a time.
+#[derive(Debug)]
+pub struct MovePicker<'a> {
+    board: &'a Board,
+    hash_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    history: Option<&'a History>,
+    stage: Stage,
+    last_stage: Stage,
+    moves: MoveList,
+    index: usize,
+    relevant_walls: SquareSet,
+}
+
+impl<'a> MovePicker<'a> {
+    /// Creates a picker over the legal moves of `board`, which yields `hash_move`
+    /// first if it is given and legal. No moves are yielded once the game is over.
+    #[must_use]
+    pub const fn new(board: &'a Board, hash_move: Option<Move>) -> Self {
+        let stage = if board.outcome().is_some() {
+            Stage::Done
+        } else {
+            Stage::HashMove
+        };
+        Self {
+            board,
+            hash_move,
+            killers: [None; 2],
+            history: None,
+            stage,
+            last_stage: Stage::OtherWalls,
+            moves: MoveList::new(),
+            index: 0,
+            relevant_walls: SquareSet::EMPTY,
+        }
+    }
+
+    /// Sets the walls to be tried once the pawn moves have been.
+    /// Killers that aren't walls, or aren't legal, are ignored.
+    #[must_use]
+    pub const fn with_killers(mut self, killers: [Option<Move>; 2]) -> Self {
+        self.killers = killers;
+        self
+    }
+
+    /// Sets the history scores by which to order the walls.
+    #[must_use]
+    pub const fn with_history(mut self, history: &'a History) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Returns the stage that the most recently yielded move came from.
+    #[must_use]
+    pub const fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// Stops the picker once it has yielded every move of `stage`,
+    /// so that the stages after it are never generated.
+    pub fn stop_after(&mut self, stage: Stage) {
+        self.last_stage = self.last_stage.min(stage);
+    }
+
+    /// Moves on to the next stage, generating its moves.
+    fn advance(&mut self) {
+        self.moves.clear();
+        self.index = 0;
+        self.stage = match self.stage {
+            Stage::HashMove => Stage::PawnMoves,
+            Stage::PawnMoves => Stage::Killers,
+            Stage::Killers => Stage::RelevantWalls,
+            Stage::RelevantWalls => Stage::OtherWalls,
+            Stage::OtherWalls | Stage::Done => Stage::Done,
+        };
+        if self.stage > self.last_stage {
+            self.stage = Stage::Done;
+        }
+
+        let moves = &mut self.moves;
+        match self.stage {
+            Stage::HashMove | Stage::Done => {}
+            Stage::PawnMoves => {
+                self.board.generate_pawn_moves(|mv| {
+                    moves.push(mv);
+                    false
+                });
+                // the moves that bring the pawn closest to its goal row come first.
+                let map = self.board.distance_map(self.board.turn());
+                moves.sort_by_score(|mv| match mv {
+                    Move::Pawn { to_square } => -i32::from(map.get(to_square).unwrap_or(u8::MAX)),
+                    Move::Wall { .. } => i32::MIN,
+                });
+            }
+            Stage::Killers => {
+                for killer in self.killers.into_iter().flatten() {
+                    let is_wall = matches!(killer, Move::Wall { .. });
+                    if is_wall && Some(killer) != self.hash_move && self.board.is_legal(killer) {
+                        moves.push(killer);
+                    }
+                }
+            }
+            Stage::RelevantWalls => {
+                self.relevant_walls = self.board.walls_near_shortest_paths();
+                self.board.generate_wall_moves(self.relevant_walls, |mv| {
+                    moves.push(mv);
+                    false
+                });
+                self.sort_by_history();
+            }
+            Stage::OtherWalls => {
+                self.board.generate_wall_moves(!self.relevant_walls, |mv| {
+                    moves.push(mv);
+                    false
+                });
+                self.sort_by_history();
+            }
+        }
+    }
+
+    fn sort_by_history(&mut self) {
+        if let Some(history) = self.history {
+            let colour = self.board.turn();
+            self.moves.sort_by_score(|mv| history.get(colour, mv));
+        }
+    }
+
+    /// Determines whether `mv` was already yielded by an earlier stage.
+    fn already_tried(&self, mv: Move) -> bool {
+        Some(mv) == self.hash_move
+            || (self.stage > Stage::Killers && self.killers.contains(&Some(mv)))
+    }
+}
+
+impl Iterator for MovePicker<'_> {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stage {
+                Stage::Done => return None,
+                Stage::HashMove => {
+                    // the index is only zero until the hash move has been tried.
+                    let untried = self.index == 0;
+                    self.index = 1;
+                    if let Some(mv) = self.hash_move.filter(|_| untried) {
+                        if self.board.is_legal(mv) {
+                            return Some(mv);
+                        }
+                    }
+                    self.advance();
+                }
+                _ => {
+                    if let Some(&mv) = self.moves.get(self.index) {
+                        self.index += 1;
+                        if !self.already_tried(mv) {
+                            return Some(mv);
+                        }
+                    } else {
+                        self.advance();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{MovePicker, Stage};
+    use crate::{board::Board, rng::XorShiftRng};
+
+    #[test]
+    fn yields_every_legal_move_once() {
+        let mut rng = XorShiftRng::new(0x51A6E);
+        for _ in 0..10 {
+            let mut board = Board::default();
+            for _ in 0..60 {
+                let legal = board.legal_moves();
+                if legal.is_empty() {
+                    break;
+                }
+                let hash_move = legal[rng.next_below(legal.len())];
+                let picked = MovePicker::new(&board, Some(hash_move)).collect::<Vec<_>>();
+                assert_eq!(
+                    picked.first(),
+                    Some(&hash_move),
+                    "hash move first in {board}"
+                );
+                assert_eq!(picked.len(), legal.len(), "wrong move count in {board}");
+                let picked = picked.into_iter().collect::<HashSet<_>>();
+                assert_eq!(
+                    picked,
+                    legal.iter().copied().collect(),
+                    "wrong moves in {board}"
+                );
+                board.make_move(hash_move);
+            }
+        }
+    }
+
+    #[test]
+    fn illegal_hash_move_is_skipped() {
+        let board = Board::default();
+        let illegal = "e3".parse().unwrap();
+        let mut picker = MovePicker::new(&board, Some(illegal));
+        assert_eq!(picker.next(), Some("e2".parse().unwrap()));
+        assert_eq!(picker.stage(), Stage::PawnMoves);
+        assert!(picker.all(|mv| mv != illegal));
+
+        let finished: Board = " /  / e9 e2 / 10 10 / 2".parse().unwrap();
+        assert_eq!(MovePicker::new(&finished, None).next(), None);
+    }
+
+    #[test]
+    fn stages_in_order() {
+        let board = Board::default();
+        let mut picker = MovePicker::new(&board, Some("a1h".parse().unwrap()));
+        let mut stages = Vec::new();
+        while let Some(mv) = picker.next() {
+            stages.push((picker.stage(), mv));
+        }
+        assert!(stages.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        assert_eq!(stages[0].0, Stage::HashMove);
+        // forward first, then the sideways moves.
+        assert_eq!(stages[1], (Stage::PawnMoves, "e2".parse().unwrap()));
+
+        // both shortest paths run up the e-file, so the relevant walls are
+        // those on the d- and e-files, of which none were the hash move.
+        let relevant = stages
+            .iter()
+            .filter(|(stage, _)| *stage == Stage::RelevantWalls)
+            .map(|(_, mv)| mv.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(relevant.len(), 32);
+        assert!(relevant
+            .iter()
+            .all(|mv| mv.starts_with('d') || mv.starts_with('e')));
+        assert_eq!(stages.len(), 131);
+    }
+
+    #[test]
+    fn stop_after_skips_stages() {
+        let board = Board::default();
+        let mut picker = MovePicker::new(&board, None);
+        picker.stop_after(Stage::PawnMoves);
+        assert_eq!(picker.count(), 3);
+
+        let mut picker = MovePicker::new(&board, None);
+        picker.stop_after(Stage::RelevantWalls);
+        assert_eq!(picker.count(), 3 + 32);
+
+        let mut picker = MovePicker::new(&board, Some("e2".parse().unwrap()));
+        picker.stop_after(Stage::HashMove);
+        assert_eq!(picker.collect::<Vec<_>>(), vec!["e2".parse().unwrap()]);
+    }
+    #[test]
+    fn killers_and_history() {
+        use super::History;
+        use crate::types::{Colour, Move};
+
+        let board = Board::default();
+        let mv = |s: &str| s.parse::<Move>().unwrap();
+        let history = History::new();
+        history.update(Colour::White, mv("e4v"), 500);
+        history.update(Colour::White, mv("a7h"), 400);
+        history.update(Colour::Black, mv("a6h"), 1000);
+
+        // e2 is a pawn move, so isn't tried as a killer.
+        let killers = [Some(mv("b5h")), Some(mv("e2"))];
+        let mut picker = MovePicker::new(&board, None)
+            .with_killers(killers)
+            .with_history(&history);
+        let mut order = Vec::new();
+        while let Some(mv) = picker.next() {
+            order.push((picker.stage(), mv.to_string()));
+        }
+        let stage_of = |name: &str| {
+            order
+                .iter()
+                .find(|(_, mv)| mv == name)
+                .map(|(stage, _)| *stage)
+        };
+        let index_of = |name: &str| order.iter().position(|(_, mv)| mv == name);
+        assert_eq!(order.len(), 131);
+        assert_eq!(index_of("b5h"), Some(3));
+        assert_eq!(stage_of("b5h"), Some(Stage::Killers));
+        assert_eq!(index_of("e4v"), Some(4));
+        assert_eq!(stage_of("e4v"), Some(Stage::RelevantWalls));
+        assert_eq!(index_of("a7h"), Some(4 + 32));
+        assert_eq!(stage_of("a7h"), Some(Stage::OtherWalls));
+        assert_eq!(order.iter().filter(|(_, mv)| mv == "b5h").count(), 1);
+
+        history.update(Colour::White, mv("e4v"), 1 << 20);
+        assert!(history.get(Colour::White, mv("e4v")) <= History::MAX);
+        history.age();
+        assert!(history.get(Colour::White, mv("e4v")) <= History::MAX / 2);
+        history.clear();
+        assert_eq!(history.get(Colour::White, mv("a7h")), 0);
+    }
+}
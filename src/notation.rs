@@ -0,0 +1,159 @@
+//! Move notations other than this crate's own.
+//!
+//! Each dialect implements [`Notation`], so that tools can be written
+//! against the trait and told which dialect to use at runtime. Moves can be
+//! translated between dialects with [`convert`].
+
+use crate::types::{Move, MoveParseError, Square8x8, Square9x9};
+
+/// A way of writing moves as text.
+pub trait Notation {
+    /// Writes a move in this notation.
+    fn format_move(&self, mv: Move) -> String;
+
+    /// Reads a move written in this notation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `text` isn't a move in this notation.
+    fn parse_move(&self, text: &str) -> Result<Move, MoveParseError>;
+}
+
+/// This crate's own notation, as used by [`Move`]'s `Display` and `FromStr` implementations.
+///
+/// Rank 1 is white's starting rank, and walls are named by
+/// the square to the south-west of their centre, as in `e4h`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Standard;
+
+impl Notation for Standard {
+    fn format_move(&self, mv: Move) -> String {
+        mv.to_string()
+    }
+
+    fn parse_move(&self, text: &str) -> Result<Move, MoveParseError> {
+        text.parse()
+    }
+}
+
+/// The notation of Lisa Glendenning's thesis, as used by Quoridor Strats and most published games.
+///
+/// Ranks are numbered from the other side of the board, so white starts on
+/// `e9`, and walls are named by the square to the north-west of their
+/// centre, so the wall written `e4h` here is written `e5h` there.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Glendenning;
+
+impl Glendenning {
+    /// Reflects a move in the line between the fourth and fifth ranks. This
+    /// turns this crate's notation into Glendenning's and back again, as
+    /// the south-west corner of a wall becomes its north-west corner.
+    const fn mirror(mv: Move) -> Move {
+        match mv {
+            Move::Pawn { to_square } => Move::Pawn {
+                to_square: Square9x9::from_file_rank(to_square.file(), 8 - to_square.rank())
+                    .expect("mirroring a square stays on the board"),
+            },
+            Move::Wall {
+                to_square,
+                orientation,
+            } => Move::Wall {
+                to_square: Square8x8::from_file_rank(to_square.file(), 7 - to_square.rank())
+                    .expect("mirroring a wall stays on the board"),
+                orientation,
+            },
+        }
+    }
+}
+
+impl Notation for Glendenning {
+    fn format_move(&self, mv: Move) -> String {
+        Self::mirror(mv).to_string()
+    }
+
+    fn parse_move(&self, text: &str) -> Result<Move, MoveParseError> {
+        text.parse().map(Self::mirror)
+    }
+}
+
+/// Translates a move written in one notation into another.
+///
+/// # Errors
+///
+/// Returns an error if `text` isn't a move in the notation `from`.
+pub fn convert(
+    text: &str,
+    from: &dyn Notation,
+    to: &dyn Notation,
+) -> Result<String, MoveParseError> {
+    from.parse_move(text).map(|mv| to.format_move(mv))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convert, Glendenning, Notation, Standard};
+    use crate::types::{Move, Square8x8, Square9x9, WallOrientation};
+
+    fn every_move() -> Vec<Move> {
+        let pawns = (0..9)
+            .flat_map(|rank| (0..9).map(move |file| (file, rank)))
+            .map(|(file, rank)| Move::Pawn {
+                to_square: Square9x9::from_file_rank(file, rank).unwrap(),
+            });
+        let walls = (0..8)
+            .flat_map(|rank| (0..8).map(move |file| (file, rank)))
+            .flat_map(|(file, rank)| {
+                let to_square = Square8x8::from_file_rank(file, rank).unwrap();
+                [WallOrientation::Horizontal, WallOrientation::Vertical].map(|orientation| {
+                    Move::Wall {
+                        to_square,
+                        orientation,
+                    }
+                })
+            });
+        pawns.chain(walls).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let moves = every_move();
+        assert_eq!(moves.len(), 81 + 128);
+        let notations: [&dyn Notation; 2] = [&Standard, &Glendenning];
+        for notation in notations {
+            let mut written = moves
+                .iter()
+                .map(|&mv| notation.format_move(mv))
+                .collect::<Vec<_>>();
+            for (&mv, text) in moves.iter().zip(&written) {
+                assert_eq!(notation.parse_move(text), Ok(mv), "{text}");
+            }
+            written.sort();
+            written.dedup();
+            assert_eq!(written.len(), moves.len());
+        }
+    }
+
+    #[test]
+    fn glendenning() {
+        for (standard, glendenning) in [
+            ("e1", "e9"),
+            ("e9", "e1"),
+            ("a5", "a5"),
+            ("e4h", "e5h"),
+            ("a1v", "a8v"),
+            ("h8h", "h1h"),
+        ] {
+            assert_eq!(
+                convert(standard, &Standard, &Glendenning).unwrap(),
+                glendenning
+            );
+            assert_eq!(
+                convert(glendenning, &Glendenning, &Standard).unwrap(),
+                standard
+            );
+        }
+        // there's no ninth rank of walls in either notation.
+        assert!(Glendenning.parse_move("e9h").is_err());
+        assert!(Glendenning.parse_move("e10").is_err());
+    }
+}
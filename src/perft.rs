@@ -1,7 +1,196 @@
-use std::{collections::HashMap, hash::BuildHasher};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    hash::BuildHasher,
+    num::ParseIntError,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    board::{Board, FenParseError},
+    tt::{TableEntry, TranspositionTable},
+    types::Move,
+};
+
+/// A store of subtree node counts for [`perft_cached`].
+pub trait PerftCache {
+    /// Returns the node count of the subtree rooted at `board` to `depth`, if known.
+    fn get(&self, board: &Board, depth: u8) -> Option<u64>;
+    /// Records the node count of the subtree rooted at `board` to `depth`.
+    fn insert(&mut self, board: &Board, depth: u8, count: u64);
+}
+
+/// Caches counts keyed on the Zobrist key of the position, trusting that
+/// distinct positions don't collide.
+impl<S: BuildHasher> PerftCache for HashMap<(u64, u8), u64, S> {
+    fn get(&self, board: &Board, depth: u8) -> Option<u64> {
+        Self::get(self, &(board.hash_key(), depth)).copied()
+    }
 
-use crate::board::Board;
+    fn insert(&mut self, board: &Board, depth: u8, count: u64) {
+        Self::insert(self, (board.hash_key(), depth), count);
+    }
+}
 
+/// Splits the perft count for `board` by root move, returning each
+/// legal move along with the number of leaf nodes beneath it.
+///
+/// The counts sum to `perft(board, depth)`, save that no moves
+/// are returned at depth zero.
+#[must_use]
+pub fn divide(board: Board, depth: u8) -> Vec<(Move, u64)> {
+    let mut counts = Vec::new();
+    if depth == 0 {
+        return counts;
+    }
+    board.generate_moves(|mv| {
+        let mut child = board;
+        child.make_move(mv);
+        counts.push((mv, perft(child, depth - 1)));
+        false
+    });
+    counts
+}
+
+/// As [`perft`], but splits the root moves between `threads` threads.
+/// If `table` is given, the threads share it as a cache, as in [`perft_cached`].
+///
+/// # Panics
+///
+/// Panics if `threads` is zero, or if a worker thread panics.
+#[allow(clippy::module_name_repetitions)]
+#[must_use]
+pub fn perft_parallel(
+    board: Board,
+    depth: u8,
+    threads: usize,
+    table: Option<&TranspositionTable<PerftEntry>>,
+) -> u64 {
+    assert!(threads > 0, "perft needs at least one thread");
+    if depth <= 1 {
+        return perft(board, depth);
+    }
+
+    let root_moves = board.legal_moves();
+
+    // threads claim root moves one at a time, so that a thread that
+    // draws a small subtree moves on to the next without waiting.
+    let next_move = AtomicUsize::new(0);
+    std::thread::scope(|s| {
+        let workers = (0..threads.min(root_moves.len()))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut count = 0;
+                    while let Some(&mv) = root_moves.get(next_move.fetch_add(1, Ordering::Relaxed))
+                    {
+                        let mut board = board;
+                        board.make_move(mv);
+                        count += table.map_or_else(
+                            || perft(board, depth - 1),
+                            |mut table| perft_cached(board, depth - 1, &mut table),
+                        );
+                    }
+                    count
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("perft worker panicked"))
+            .sum()
+    })
+}
+
+/// A subtree node count, as stored in a [`TranspositionTable`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PerftEntry {
+    count: u64,
+    depth: u8,
+}
+
+impl PerftEntry {
+    /// The largest count that fits in an entry.
+    const MAX_COUNT: u64 = (1 << 48) - 1;
+}
+
+impl TableEntry for PerftEntry {
+    fn pack(self) -> u64 {
+        self.count | u64::from(self.depth) << 48
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn unpack(data: u64) -> Self {
+        Self {
+            count: data & Self::MAX_COUNT,
+            depth: (data >> 48) as u8,
+        }
+    }
+
+    fn depth(self) -> u8 {
+        self.depth
+    }
+}
+
+/// Caches counts in a fixed-size table, which may be shared between threads.
+/// Counts too large to fit in a [`PerftEntry`] are not cached.
+impl PerftCache for &TranspositionTable<PerftEntry> {
+    fn get(&self, board: &Board, depth: u8) -> Option<u64> {
+        self.probe(perft_key(board, depth))
+            .filter(|entry| entry.depth == depth)
+            .map(|entry| entry.count)
+    }
+
+    fn insert(&mut self, board: &Board, depth: u8, count: u64) {
+        if count <= PerftEntry::MAX_COUNT {
+            self.store(perft_key(board, depth), PerftEntry { count, depth });
+        }
+    }
+}
+
+/// Mixes the depth into the key of a position, so that counts for
+/// the same position at different depths occupy different slots.
+fn perft_key(board: &Board, depth: u8) -> u64 {
+    board.hash_key() ^ u64::from(depth).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+/// A cache that stores the full board alongside each count, and panics
+/// if two distinct positions are ever found to share a Zobrist key.
+///
+/// This is much slower and larger than keying on the Zobrist key alone,
+/// and is intended for testing the hashing scheme.
+#[derive(Debug, Default)]
+pub struct VerifyingCache {
+    entries: HashMap<(u64, u8), (Board, u64)>,
+}
+
+impl VerifyingCache {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl PerftCache for VerifyingCache {
+    fn get(&self, board: &Board, depth: u8) -> Option<u64> {
+        let (stored, count) = self.entries.get(&(board.hash_key(), depth))?;
+        assert_eq!(
+            stored,
+            board,
+            "Zobrist key collision: {stored} and {board} share key {:#018x}",
+            board.hash_key()
+        );
+        Some(*count)
+    }
+
+    fn insert(&mut self, board: &Board, depth: u8, count: u64) {
+        self.entries
+            .insert((board.hash_key(), depth), (*board, count));
+    }
+}
+
+/// Counts the leaf nodes of the game tree rooted at `board`, `depth` plies deep.
+/// Finished games have no moves, so a game that ends before `depth` plies
+/// contributes no leaves.
 #[must_use]
 pub fn perft(board: Board, depth: u8) -> u64 {
     if depth == 0 {
@@ -27,13 +216,10 @@ pub fn perft(board: Board, depth: u8) -> u64 {
     count
 }
 
+/// As [`perft`], but memoises subtree counts in `cache`.
 #[allow(clippy::module_name_repetitions)]
 #[must_use]
-pub fn perft_cached<S: BuildHasher>(
-    board: Board,
-    depth: u8,
-    cache: &mut HashMap<(Board, u8), u64, S>,
-) -> u64 {
+pub fn perft_cached(board: Board, depth: u8, cache: &mut impl PerftCache) -> u64 {
     if depth == 0 {
         return 1;
     }
@@ -47,7 +233,7 @@ pub fn perft_cached<S: BuildHasher>(
         return count;
     }
 
-    if let Some(&count) = cache.get(&(board, depth)) {
+    if let Some(count) = cache.get(&board, depth) {
         return count;
     }
 
@@ -59,14 +245,140 @@ pub fn perft_cached<S: BuildHasher>(
         false
     });
 
-    cache.insert((board, depth), count);
+    cache.insert(&board, depth, count);
 
     count
 }
 
+/// A position from a perft suite, along with its expected node counts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SuiteEntry {
+    /// The line of the suite on which the position appears, counting from one.
+    pub line: usize,
+    pub board: Board,
+    /// Pairs of depth and expected node count.
+    pub expected: Vec<(u8, u64)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SuiteParseError {
+    Fen { line: usize, error: FenParseError },
+    MissingDepthPrefix { line: usize, field: String },
+    MissingCount { line: usize, field: String },
+    DepthParseError { line: usize, error: ParseIntError },
+    CountParseError { line: usize, error: ParseIntError },
+}
+
+/// A position whose perft count at some depth didn't match the suite.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SuiteMismatch {
+    pub line: usize,
+    pub board: Board,
+    pub depth: u8,
+    pub expected: u64,
+    pub actual: u64,
+    /// The node counts at `depth`, split by root move.
+    pub divide: Vec<(Move, u64)>,
+}
+
+impl Display for SuiteMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "mismatch on line {}: {}", self.line, self.board)?;
+        writeln!(
+            f,
+            "perft({}) = {}, expected {}",
+            self.depth, self.actual, self.expected
+        )?;
+        for (mv, count) in &self.divide {
+            writeln!(f, "{mv}: {count}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a perft suite, made up of lines of the form
+/// `fen ; D1 count ; D2 count ; ...`. Blank lines and
+/// lines beginning with `#` are ignored.
+///
+/// # Errors
+///
+/// Returns an error describing the first malformed line.
+pub fn parse_suite(text: &str) -> Result<Vec<SuiteEntry>, SuiteParseError> {
+    let mut entries = Vec::new();
+    for (index, text) in text.lines().enumerate() {
+        let line = index + 1;
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let mut fields = text.split(';').map(str::trim);
+        let fen = fields.next().unwrap_or_default();
+        let board = Board::from_fen(fen).map_err(|error| SuiteParseError::Fen { line, error })?;
+        let mut expected = Vec::new();
+        for field in fields.filter(|field| !field.is_empty()) {
+            let Some(rest) = field.strip_prefix('D') else {
+                return Err(SuiteParseError::MissingDepthPrefix {
+                    line,
+                    field: field.to_string(),
+                });
+            };
+            let Some((depth, count)) = rest.split_once(char::is_whitespace) else {
+                return Err(SuiteParseError::MissingCount {
+                    line,
+                    field: field.to_string(),
+                });
+            };
+            let depth = depth
+                .parse()
+                .map_err(|error| SuiteParseError::DepthParseError { line, error })?;
+            let count = count
+                .trim()
+                .parse()
+                .map_err(|error| SuiteParseError::CountParseError { line, error })?;
+            expected.push((depth, count));
+        }
+        entries.push(SuiteEntry {
+            line,
+            board,
+            expected,
+        });
+    }
+    Ok(entries)
+}
+
+/// Runs every position in a perft suite, returning the mismatches.
+/// Only the shallowest mismatching depth of each position is reported,
+/// since every deeper count will almost certainly be wrong too.
+#[must_use]
+pub fn run_suite(entries: &[SuiteEntry]) -> Vec<SuiteMismatch> {
+    let mut mismatches = Vec::new();
+    for entry in entries {
+        let mut expected = entry.expected.clone();
+        expected.sort_unstable();
+        for (depth, expected) in expected {
+            let actual = perft(entry.board, depth);
+            if actual != expected {
+                mismatches.push(SuiteMismatch {
+                    line: entry.line,
+                    board: entry.board,
+                    depth,
+                    expected,
+                    actual,
+                    divide: divide(entry.board, depth),
+                });
+                break;
+            }
+        }
+    }
+    mismatches
+}
+
 #[cfg(test)]
 mod tests {
-    use std::{collections::HashSet, str::FromStr};
+    use std::{
+        collections::{HashMap, HashSet},
+        str::FromStr,
+    };
 
     #[test]
     fn perft_depth_one() {
@@ -523,4 +835,546 @@ mod tests {
 
         assert_eq!(count, 4, "perft(1) = {count}");
     }
+    #[test]
+    fn straight_jump_move_names() {
+        use crate::board::Board;
+        use crate::types::Move;
+
+        let mut board = Board::default();
+        board.set_walls_in_pocket(0, 0);
+        for mv in ["e2", "e8", "e3", "e7", "e4", "e6", "e5"] {
+            board.make_move(Move::from_str(mv).unwrap());
+        }
+
+        // black is on e6, facing white on e5, so black
+        // can jump straight over white to e4.
+        let mut moves = HashSet::new();
+        board.generate_moves(|mv| {
+            moves.insert(mv.to_string());
+            false
+        });
+
+        let reference_movelist = ["e7", "d6", "f6", "e4"]
+            .into_iter()
+            .map(ToString::to_string)
+            .collect::<HashSet<_>>();
+
+        assert_eq!(
+            moves, reference_movelist,
+            "moves generated must match reference"
+        );
+    }
+
+    #[test]
+    fn diagonal_jump_wall_behind_move_names() {
+        use crate::board::Board;
+        use crate::types::Move;
+
+        let mut board = Board::default();
+        for mv in ["e2", "e8", "e3", "e7", "e4", "e6", "d3h", "e5"] {
+            board.make_move(Move::from_str(mv).unwrap());
+        }
+        board.pass_turn();
+        board.set_walls_in_pocket(0, 0);
+
+        // black is on e5, facing white on e4. d3h sits
+        // behind white, so black must jump diagonally.
+        let mut moves = HashSet::new();
+        board.generate_moves(|mv| {
+            moves.insert(mv.to_string());
+            false
+        });
+
+        let reference_movelist = ["e6", "d5", "f5", "d4", "f4"]
+            .into_iter()
+            .map(ToString::to_string)
+            .collect::<HashSet<_>>();
+
+        assert_eq!(
+            moves, reference_movelist,
+            "moves generated must match reference"
+        );
+    }
+
+    #[test]
+    fn diagonal_jump_one_side_walled_move_names() {
+        use crate::board::Board;
+        use crate::types::Move;
+
+        let mut board = Board::default();
+        for mv in ["e2", "e8", "e3", "e7", "e4", "e6", "d3h", "e5", "d4v"] {
+            board.make_move(Move::from_str(mv).unwrap());
+        }
+        board.set_walls_in_pocket(0, 0);
+
+        // as before, but d4v now walls off the diagonal to d4,
+        // as well as the step west to d5.
+        let mut moves = HashSet::new();
+        board.generate_moves(|mv| {
+            moves.insert(mv.to_string());
+            false
+        });
+
+        let reference_movelist = ["e6", "f5", "f4"]
+            .into_iter()
+            .map(ToString::to_string)
+            .collect::<HashSet<_>>();
+
+        assert_eq!(
+            moves, reference_movelist,
+            "moves generated must match reference"
+        );
+    }
+
+    #[test]
+    fn diagonal_jump_board_edge_move_names() {
+        use crate::board::Board;
+        use crate::types::Move;
+
+        let mut board = Board::default();
+        board.set_walls_in_pocket(0, 0);
+        for mv in ["e2", "e3", "e4", "e5", "e6", "e7", "e8"] {
+            board.make_move(Move::from_str(mv).unwrap());
+            board.pass_turn();
+        }
+
+        // white is on e8, facing black on e9. the edge of the
+        // board is behind black, so white must jump diagonally.
+        let mut moves = HashSet::new();
+        board.generate_moves(|mv| {
+            moves.insert(mv.to_string());
+            false
+        });
+
+        let reference_movelist = ["e7", "d8", "f8", "d9", "f9"]
+            .into_iter()
+            .map(ToString::to_string)
+            .collect::<HashSet<_>>();
+
+        assert_eq!(
+            moves, reference_movelist,
+            "moves generated must match reference"
+        );
+    }
+
+    #[test]
+    fn perft_jumps_nowall() {
+        use crate::board::Board;
+        use crate::types::Move;
+
+        let mut board = Board::default();
+        board.set_walls_in_pocket(0, 0);
+        for mv in ["e2", "e8", "e3", "e7", "e4", "e6"] {
+            board.make_move(Move::from_str(mv).unwrap());
+        }
+
+        // white on e4 and black on e6: every one of white's four moves
+        // leaves black with four replies. after e5 the pawns are face
+        // to face, so one of those replies is the straight jump to e4.
+        let count = super::perft(board, 1);
+        assert_eq!(count, 4, "perft(1) = {count}");
+
+        let count = super::perft(board, 2);
+        assert_eq!(count, 4 + 4 + 4 + 4, "perft(2) = {count}");
+    }
+
+    #[test]
+    fn i_file_pawn_not_blocked_by_a_file_wall() {
+        use crate::board::Board;
+        use crate::types::Move;
+
+        let mut board = Board::default();
+        // a2h sits above a2 and b2, and has nothing to do with i1.
+        board.make_move(Move::from_str("a2h").unwrap());
+        board.pass_turn();
+        board.set_walls_in_pocket(0, 0);
+        for mv in ["f1", "g1", "h1", "i1"] {
+            board.make_move(Move::from_str(mv).unwrap());
+            board.pass_turn();
+        }
+
+        let mut moves = HashSet::new();
+        board.generate_moves(|mv| {
+            moves.insert(mv.to_string());
+            false
+        });
+
+        let reference_movelist = ["i2", "h1"]
+            .into_iter()
+            .map(ToString::to_string)
+            .collect::<HashSet<_>>();
+
+        assert_eq!(
+            moves, reference_movelist,
+            "moves generated must match reference"
+        );
+    }
+    #[test]
+    fn sealing_wall_excluded() {
+        use crate::board::Board;
+        use crate::types::{Move, WallOrientation};
+
+        let mut board = Board::default();
+        // a barrier across the board above the fifth rank,
+        // with a single gap in the i-file. h5v walls off the
+        // gap from the h-file, so the only way through is the
+        // i-file corridor from i4 up to i7.
+        for mv in ["a5h", "c5h", "e5h", "g5h", "h5v"] {
+            board.make_move(Move::from_str(mv).unwrap());
+            board.pass_turn();
+        }
+
+        // h6h or h4h would close off the corridor, sealing
+        // both players in their halves of the board.
+        let h6 = Move::from_str("h6h").unwrap().wall_to_square().unwrap();
+        assert!(!board.is_legal_wall(h6, WallOrientation::Horizontal));
+        let h4 = Move::from_str("h4h").unwrap().wall_to_square().unwrap();
+        assert!(!board.is_legal_wall(h4, WallOrientation::Horizontal));
+        // h7v runs alongside the corridor, but leaves it open.
+        let h7 = Move::from_str("h7v").unwrap().wall_to_square().unwrap();
+        assert!(board.is_legal_wall(h7, WallOrientation::Vertical));
+
+        let mut moves = HashSet::new();
+        board.generate_moves(|mv| {
+            moves.insert(mv.to_string());
+            false
+        });
+        assert!(!moves.contains("h6h"), "h6h seals off both players");
+        assert!(!moves.contains("h4h"), "h4h seals off both players");
+
+        // three pawn moves, 64 - 8 - 2 horizontal walls
+        // (the fifth rank is full, and h4h and h6h are illegal),
+        // and 64 - 7 vertical walls.
+        let count = super::perft(board, 1);
+        assert_eq!(count, 3 + 54 + 57, "perft(1) = {count}");
+    }
+
+    #[test]
+    fn sealing_empty_region_allowed() {
+        use crate::board::Board;
+        use crate::types::{Move, WallOrientation};
+
+        let mut board = Board::default();
+        board.make_move(Move::from_str("a2h").unwrap());
+        board.pass_turn();
+
+        // b1v would close off a1, b1, a2, and b2, but
+        // neither pawn is in there, so this is legal.
+        let b1 = Move::from_str("b1v").unwrap().wall_to_square().unwrap();
+        assert!(board.is_legal_wall(b1, WallOrientation::Vertical));
+
+        let mut moves = HashSet::new();
+        board.generate_moves(|mv| {
+            moves.insert(mv.to_string());
+            false
+        });
+        assert!(moves.contains("b1v"), "b1v seals off an empty region");
+    }
+
+    #[test]
+    fn legal_moves_match_generate_moves() {
+        use crate::board::Board;
+        use crate::rng::XorShiftRng;
+
+        let mut rng = XorShiftRng::new(0x5EED);
+        for _ in 0..20 {
+            let mut board = Board::default();
+            for _ in 0..60 {
+                let mut generated = Vec::new();
+                board.generate_moves(|mv| {
+                    generated.push(mv);
+                    false
+                });
+                let moves = board.legal_moves();
+                assert_eq!(&moves[..], &generated[..], "in {board}");
+                for &mv in &generated {
+                    assert!(moves.contains(mv));
+                }
+                if moves.is_empty() {
+                    break;
+                }
+                board.make_move(moves[rng.next_below(moves.len())]);
+            }
+        }
+    }
+
+    #[test]
+    fn legal_moves_in_finished_games() {
+        use crate::board::Board;
+
+        let board: Board = " /  / e9 e5 / 10 10 / 2".parse().unwrap();
+        assert!(board.legal_moves().is_empty());
+        assert_eq!(super::perft(board, 1), 0);
+    }
+
+    #[test]
+    fn sealing_corner_pawn_excluded() {
+        use crate::board::Board;
+        use crate::types::{Move, WallOrientation};
+
+        let mut board = Board::default();
+        board.set_walls_in_pocket(1, 1);
+        for mv in ["d1", "c1", "b1", "a1"] {
+            board.make_move(Move::from_str(mv).unwrap());
+            board.pass_turn();
+        }
+        board.make_move(Move::from_str("a2h").unwrap());
+        board.pass_turn();
+
+        // now white is on a1, so closing off the corner is illegal.
+        let b1 = Move::from_str("b1v").unwrap().wall_to_square().unwrap();
+        assert!(!board.is_legal_wall(b1, WallOrientation::Vertical));
+        // black has a wall, but white doesn't.
+        board.pass_turn();
+        assert!(!board.is_legal_wall(b1, WallOrientation::Vertical));
+        board.pass_turn();
+        let c1 = Move::from_str("c1v").unwrap().wall_to_square().unwrap();
+        assert!(!board.is_legal_wall(c1, WallOrientation::Vertical));
+        board.pass_turn();
+        assert!(board.is_legal_wall(c1, WallOrientation::Vertical));
+    }
+
+    #[test]
+    fn perft_sealed_corridor() {
+        use crate::board::Board;
+        use crate::types::Move;
+
+        let mut board = Board::default();
+        for mv in ["a5h", "c5h", "e5h", "g5h", "h5v"] {
+            board.make_move(Move::from_str(mv).unwrap());
+            board.pass_turn();
+        }
+
+        let count = super::perft(board, 2);
+        assert_eq!(count, 12_575, "perft(2) = {count}");
+    }
+    #[test]
+    fn outcome_detection() {
+        use crate::board::Board;
+        use crate::types::{Colour, GameResult, Move};
+
+        let mut board = Board::default();
+        board.set_walls_in_pocket(0, 0);
+        assert_eq!(board.outcome(), None);
+        for mv in ["e2", "e3", "e4", "e5", "e6", "e7", "e8"] {
+            board.make_move(Move::from_str(mv).unwrap());
+            board.pass_turn();
+        }
+        board.pass_turn();
+        for mv in ["d9", "c9", "b9", "a9"] {
+            board.make_move(Move::from_str(mv).unwrap());
+            board.pass_turn();
+        }
+        board.pass_turn();
+        assert_eq!(board.outcome(), None);
+        assert_eq!(board.turn(), Colour::White);
+
+        board.make_move(Move::from_str("e9").unwrap());
+        assert_eq!(board.outcome(), Some(GameResult::WhiteWin));
+        assert_eq!(board.outcome().map(GameResult::winner), Some(Colour::White));
+        assert_eq!(super::perft(board, 1), 0, "no moves in a finished game");
+        assert_eq!(super::perft(board, 0), 1, "the finished game is itself a node");
+    }
+
+    #[test]
+    fn perft_terminal_nodes() {
+        use crate::board::Board;
+        use crate::types::Move;
+
+        let mut board = Board::default();
+        board.set_walls_in_pocket(0, 0);
+        for mv in ["e2", "e3", "e4", "e5", "e6", "e7", "e8"] {
+            board.make_move(Move::from_str(mv).unwrap());
+            board.pass_turn();
+        }
+        board.pass_turn();
+        for mv in ["d9", "c9", "b9", "a9"] {
+            board.make_move(Move::from_str(mv).unwrap());
+            board.pass_turn();
+        }
+        board.pass_turn();
+
+        // white on e8 can win with e9, after which the search
+        // tree must stop rather than continuing to expand.
+        let expected = [1, 4, 6, 24, 60];
+        for (depth, &expected) in (0..).zip(&expected) {
+            let count = super::perft(board, depth);
+            assert_eq!(count, expected, "perft({depth}) = {count}");
+            let count = super::perft_cached(board, depth, &mut super::VerifyingCache::new());
+            assert_eq!(count, expected, "perft_cached({depth}) = {count}");
+        }
+
+        board.set_walls_in_pocket(1, 1);
+        let count = super::perft(board, 3);
+        assert_eq!(count, 112_602, "perft(3) = {count}");
+    }
+    #[test]
+    fn perft_cached_verified() {
+        use crate::board::Board;
+
+        // d3h and d4h, e3v and e4v, and so on all transpose into
+        // one another, so the cache sees plenty of hits.
+        let board = Board::default();
+        let mut cache = super::VerifyingCache::new();
+        let count = super::perft_cached(board, 3, &mut cache);
+        let mut cache = HashMap::new();
+        assert_eq!(super::perft_cached(board, 3, &mut cache), count);
+        assert_eq!(count, super::perft(board, 3), "perft(3) = {count}");
+        assert_eq!(count, 2_062_264, "perft(3) = {count}");
+    }
+
+    #[test]
+    fn hash_key_incremental() {
+        use crate::board::Board;
+        use crate::position::Position;
+        use crate::rng::XorShiftRng;
+
+        let mut rng = XorShiftRng::new(0x2B7);
+        for _ in 0..20 {
+            let mut position = Position::default();
+            let mut keys = vec![position.board().hash_key()];
+            for _ in 0..80 {
+                let mut moves = Vec::new();
+                position.board().generate_moves(|mv| {
+                    moves.push(mv);
+                    false
+                });
+                if moves.is_empty() {
+                    break;
+                }
+                position.make_move(moves[rng.next_below(moves.len())]);
+                let board = position.board();
+                let from_scratch = Board::from_fen(&board.to_string()).unwrap();
+                assert_eq!(board.hash_key(), from_scratch.hash_key(), "{board}");
+                keys.push(board.hash_key());
+            }
+            while position.unmake_move().is_some() {
+                keys.pop();
+                assert_eq!(position.board().hash_key(), *keys.last().unwrap());
+            }
+        }
+    }
+    #[test]
+    fn perft_cached_table() {
+        use crate::board::Board;
+        use crate::tt::TranspositionTable;
+
+        let board: Board = "e4 / d6 / e5 e6 / 3 10 / 1".parse().unwrap();
+        let expected = super::perft(board, 3);
+        // a tiny table forces plenty of replacement.
+        let table = TranspositionTable::new(0);
+        assert_eq!(super::perft_cached(board, 3, &mut &table), expected);
+        let table = TranspositionTable::new(16);
+        assert_eq!(super::perft_cached(board, 3, &mut &table), expected);
+        // and again, now that the table is warm.
+        assert_eq!(super::perft_cached(board, 3, &mut &table), expected);
+    }
+    #[test]
+    fn perft_parallel_matches_serial() {
+        use crate::board::Board;
+        use crate::tt::TranspositionTable;
+
+        for (fen, max_depth) in [
+            (" /  / e1 e9 / 10 10 / 1", 2),
+            ("e4 / d6 / e5 e6 / 2 1 / 1", 3),
+            ("a5c5e5g5 / h5 / e1 e9 / 5 10 / 1", 2),
+            (" /  / e8 a9 / 0 0 / 1", 5),
+        ] {
+            let board: Board = fen.parse().unwrap();
+            for depth in 0..=max_depth {
+                let expected = super::perft(board, depth);
+                for threads in [1, 4] {
+                    let count = super::perft_parallel(board, depth, threads, None);
+                    assert_eq!(
+                        count, expected,
+                        "{fen}: perft({depth}) with {threads} threads"
+                    );
+                    let table = TranspositionTable::new(1);
+                    let count = super::perft_parallel(board, depth, threads, Some(&table));
+                    assert_eq!(
+                        count, expected,
+                        "{fen}: cached perft({depth}) with {threads} threads"
+                    );
+                }
+            }
+        }
+    }
+    #[test]
+    fn divide_sums_to_perft() {
+        use crate::board::Board;
+
+        let board: Board = "e4 / d6 / e5 e6 / 3 10 / 1".parse().unwrap();
+        for depth in 0..=2 {
+            let divided = super::divide(board, depth);
+            let total = divided.iter().map(|(_, count)| count).sum::<u64>();
+            if depth == 0 {
+                assert!(divided.is_empty());
+            } else {
+                assert_eq!(total, super::perft(board, depth));
+                assert_eq!(divided.len() as u64, super::perft(board, 1));
+            }
+        }
+    }
+
+    #[test]
+    fn suite_file_passes() {
+        let mut entries = super::parse_suite(include_str!("../suites/perft.epd")).unwrap();
+        assert!(!entries.is_empty());
+        // the deeper counts take too long to check in a debug build.
+        for entry in &mut entries {
+            entry.expected.retain(|&(depth, _)| depth <= 2);
+        }
+        let mismatches = super::run_suite(&entries);
+        assert!(
+            mismatches.is_empty(),
+            "{}",
+            mismatches
+                .iter()
+                .map(ToString::to_string)
+                .collect::<String>()
+        );
+    }
+
+    #[test]
+    fn suite_reports_mismatch() {
+        use super::SuiteParseError;
+
+        let suite = "
+            # the start position, with a deliberately wrong count.
+             /  / e1 e9 / 10 10 / 1 ; D1 131 ; D2 16676
+
+             /  / e4 e6 / 0 0 / 2 ; D1 4 ; D2 16 ;
+        ";
+        let entries = super::parse_suite(suite).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].line, 3);
+        assert_eq!(entries[1].expected, vec![(1, 4), (2, 16)]);
+
+        let mismatches = super::run_suite(&entries);
+        assert_eq!(mismatches.len(), 1);
+        let mismatch = &mismatches[0];
+        assert_eq!((mismatch.line, mismatch.depth), (3, 2));
+        assert_eq!((mismatch.expected, mismatch.actual), (16_676, 16_677));
+        assert_eq!(mismatch.divide.len(), 131);
+        let report = mismatch.to_string();
+        assert!(report.contains("perft(2) = 16677, expected 16676"));
+        assert!(report.contains("\na1h: 128\n"), "{report}");
+
+        assert!(matches!(
+            super::parse_suite(" /  / e1 e9 / 10 10 / 1 ; 1 131"),
+            Err(SuiteParseError::MissingDepthPrefix { line: 1, .. })
+        ));
+        assert!(matches!(
+            super::parse_suite(" /  / e1 e9 / 10 10 / 1 ; D1"),
+            Err(SuiteParseError::MissingCount { line: 1, .. })
+        ));
+        assert!(matches!(
+            super::parse_suite(" /  / e1 e9 / 10 10 / 1 ; D1 x"),
+            Err(SuiteParseError::CountParseError { line: 1, .. })
+        ));
+        assert!(matches!(
+            super::parse_suite(" /  / e1 e9 / 10 10 ; D1 131"),
+            Err(SuiteParseError::Fen { line: 1, .. })
+        ));
+    }
 }
@@ -0,0 +1,149 @@
+use crate::board::Board;
+use crate::types::{GameResult, Move, Square9x9};
+
+/// A move that has been made, along with what's needed to take it back.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct HistoryEntry {
+    mv: Move,
+    pawn_from: Square9x9,
+}
+
+/// A board along with the stack of moves that led to it,
+/// allowing moves to be taken back without copying the board.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Position {
+    board: Board,
+    history: Vec<HistoryEntry>,
+}
+
+impl Position {
+    /// Creates a position with no history from the given board.
+    #[must_use]
+    pub const fn new(board: Board) -> Self {
+        Self {
+            board,
+            history: Vec::new(),
+        }
+    }
+
+    /// Returns the current board.
+    #[must_use]
+    pub const fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Returns the result of the game, if it has finished.
+    #[must_use]
+    pub const fn outcome(&self) -> Option<GameResult> {
+        self.board.outcome()
+    }
+
+    /// Returns the moves made since this position was created, oldest first.
+    #[must_use]
+    pub fn moves(&self) -> impl DoubleEndedIterator<Item = Move> + ExactSizeIterator + '_ {
+        self.history.iter().map(|entry| entry.mv)
+    }
+
+    /// Returns the last move made, if any.
+    #[must_use]
+    pub fn last_move(&self) -> Option<Move> {
+        self.history.last().map(|entry| entry.mv)
+    }
+
+    /// Makes a move, recording it so that it can be taken back.
+    pub fn make_move(&mut self, mv: Move) {
+        let pawn_from = self.board.pawn(self.board.turn());
+        self.board.make_move(mv);
+        self.history.push(HistoryEntry { mv, pawn_from });
+    }
+
+    /// Takes back the last move made, returning it.
+    /// Returns `None` if no moves have been made.
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let HistoryEntry { mv, pawn_from } = self.history.pop()?;
+        self.board.unmake_move(mv, pawn_from);
+        Some(mv)
+    }
+}
+
+impl From<Board> for Position {
+    fn from(board: Board) -> Self {
+        Self::new(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Position;
+    use crate::board::Board;
+    use crate::rng::XorShiftRng;
+    use crate::types::Move;
+
+    #[test]
+    #[allow(clippy::unreadable_literal)]
+    fn make_unmake_identity_random_playouts() {
+        let mut rng = XorShiftRng::new(0xDEC0DE);
+        for _ in 0..50 {
+            let mut position = Position::default();
+            let mut boards = vec![*position.board()];
+            for _ in 0..80 {
+                let mut moves = Vec::new();
+                position.board().generate_moves(|mv| {
+                    moves.push(mv);
+                    false
+                });
+                if moves.is_empty() {
+                    break;
+                }
+                let mv = moves[rng.next_below(moves.len())];
+
+                // every legal move must be exactly undone, not just the one we play.
+                for &mv in &moves {
+                    position.make_move(mv);
+                    assert_eq!(position.unmake_move(), Some(mv));
+                    assert_eq!(
+                        position.board(),
+                        boards.last().unwrap(),
+                        "make/unmake of {mv} isn't an identity"
+                    );
+                }
+
+                position.make_move(mv);
+                boards.push(*position.board());
+            }
+
+            // now unwind the whole game.
+            boards.pop();
+            while let Some(mv) = position.unmake_move() {
+                let expected = boards.pop().unwrap();
+                assert_eq!(
+                    position.board(),
+                    &expected,
+                    "unmaking {mv} didn't restore the board"
+                );
+            }
+            assert!(boards.is_empty());
+            assert_eq!(position.board(), &Board::default());
+        }
+    }
+
+    #[test]
+    #[allow(clippy::needless_collect)]
+    fn unmake_restores_walls_in_pocket() {
+        use crate::types::Colour;
+        use std::str::FromStr;
+
+        let mut position = Position::default();
+        position.make_move(Move::from_str("e4h").unwrap());
+        position.make_move(Move::from_str("e8").unwrap());
+        assert_eq!(position.board().walls_in_pocket(Colour::White), 9);
+        assert_eq!(position.moves().collect::<Vec<_>>().len(), 2);
+        assert_eq!(position.last_move(), Some(Move::from_str("e8").unwrap()));
+
+        assert_eq!(position.unmake_move(), Some(Move::from_str("e8").unwrap()));
+        assert_eq!(position.unmake_move(), Some(Move::from_str("e4h").unwrap()));
+        assert_eq!(position.unmake_move(), None);
+        assert_eq!(position.board().walls_in_pocket(Colour::White), 10);
+        assert_eq!(position.board().ply(), 0);
+    }
+}
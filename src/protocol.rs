@@ -0,0 +1,770 @@
+//! A line-based text protocol for driving the engine, modelled on UCI.
+//!
+//! The protocol is spoken over a pair of streams, one command per line:
+//!
+//! - `uci`: replies with the engine's name, options, and `uciok`.
+//! - `isready`: replies `readyok`.
+//! - `ucinewgame`: forgets everything learned from earlier searches.
+//! - `position (startpos | fen <fen>) [moves <move>...]`: sets the position.
+//! - `go [depth <n>] [nodes <n>] [movetime <ms>] [wtime <ms>] [btime <ms>]
+//!   [winc <ms>] [binc <ms>] [movestogo <n>] [infinite]`: searches the
+//!   position, reporting `info` lines as it goes and `bestmove` at the end.
+//! - `go perft <n>`: counts the positions `n` plies ahead, split by move.
+//! - `stop`: stops the search in progress.
+//! - `setoption name <name> [value <value>]`: sets an option.
+//! - `quit`: stops the search in progress and exits.
+//!
+//! Searches run in the background, so the engine keeps reading commands
+//! while searching. A position given as a FEN may span several words, as
+//! the FEN format itself contains spaces.
+
+use std::{
+    io::{BufRead, Write},
+    num::ParseIntError,
+    ops::RangeInclusive,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::{Board, FenParseError},
+    eval::Weights,
+    perft,
+    search::{plies_to_win, Limits, SearchResult, Searcher},
+    types::{Colour, Move, MoveParseError},
+};
+
+/// The size of the transposition table, if not set with the `Hash` option.
+pub const DEFAULT_HASH_MEGABYTES: usize = 16;
+
+/// The range of transposition table sizes, in megabytes, accepted for
+/// the `Hash` option.
+pub const HASH_RANGE: RangeInclusive<usize> = 1..=65536;
+
+/// The number of moves assumed to be left in the game when dividing up the
+/// remaining time, if the controller doesn't say with `movestogo`.
+const DEFAULT_MOVES_TO_GO: u32 = 25;
+
+/// The range of values accepted for each evaluation weight. Larger weights
+/// would push ordinary positions to the evaluation's bounds.
+pub const WEIGHT_RANGE: RangeInclusive<i32> = -1000..=1000;
+
+/// Time kept in reserve when thinking on the clock, to allow for the delay
+/// between the engine sending its move and the controller stopping its clock.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+/// The parameters of a `go` command.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Go {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+    pub infinite: bool,
+    /// Runs a perft to this depth instead of a search.
+    pub perft: Option<u8>,
+}
+
+impl Go {
+    /// Returns the limits of a search for the given side to move.
+    /// With a clock, the side to move spends an even share of its remaining
+    /// time over the moves left, plus half its increment. If only the
+    /// opponent's clock is given, it is taken to be the side to move's too.
+    #[must_use]
+    pub fn limits(&self, turn: Colour) -> Limits {
+        if self.infinite {
+            return Limits::default();
+        }
+        let (remaining, increment) = match turn {
+            Colour::White => (self.wtime, self.winc),
+            Colour::Black => (self.btime, self.binc),
+        };
+        let (remaining, increment) = match (remaining, turn) {
+            (Some(_), _) => (remaining, increment),
+            (None, Colour::White) => (self.btime, self.binc),
+            (None, Colour::Black) => (self.wtime, self.winc),
+        };
+        let allotted = remaining.map(|remaining| {
+            let moves_to_go = self.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+            let share = remaining / moves_to_go + increment.unwrap_or_default() / 2;
+            share
+                .min(remaining.saturating_sub(MOVE_OVERHEAD))
+                .max(Duration::from_millis(1))
+        });
+        Limits {
+            depth: self.depth,
+            nodes: self.nodes,
+            time: self.movetime.or(allotted),
+        }
+    }
+}
+
+/// A command sent to the engine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Uci,
+    IsReady,
+    NewGame,
+    Position { board: Board, moves: Vec<Move> },
+    Go(Go),
+    Stop,
+    SetOption { name: String, value: String },
+    Quit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandParseError {
+    Empty,
+    UnknownCommand(String),
+    MissingPosition,
+    Fen(FenParseError),
+    Move(MoveParseError),
+    UnknownGoParameter(String),
+    MissingValue(String),
+    ValueParseError { name: String, error: ParseIntError },
+    MissingOptionName,
+}
+
+impl FromStr for Command {
+    type Err = CommandParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let Some(command) = words.next() else {
+            return Err(CommandParseError::Empty);
+        };
+        match command {
+            "uci" => Ok(Self::Uci),
+            "isready" => Ok(Self::IsReady),
+            "ucinewgame" => Ok(Self::NewGame),
+            "position" => parse_position(words),
+            "go" => parse_go(words).map(Self::Go),
+            "stop" => Ok(Self::Stop),
+            "setoption" => parse_setoption(words),
+            "quit" => Ok(Self::Quit),
+            _ => Err(CommandParseError::UnknownCommand(command.to_string())),
+        }
+    }
+}
+
+fn parse_position<'a>(
+    mut words: impl Iterator<Item = &'a str>,
+) -> Result<Command, CommandParseError> {
+    let board = match words.next() {
+        Some("startpos") => {
+            // anything but the move list is ignored, as in UCI.
+            words.by_ref().find(|&word| word == "moves");
+            Board::default()
+        }
+        Some("fen") => {
+            let fen = words
+                .by_ref()
+                .take_while(|&word| word != "moves")
+                .collect::<Vec<_>>();
+            Board::from_fen(&fen.join(" ")).map_err(CommandParseError::Fen)?
+        }
+        _ => return Err(CommandParseError::MissingPosition),
+    };
+    let moves = words
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .map_err(CommandParseError::Move)?;
+    Ok(Command::Position { board, moves })
+}
+
+fn parse_go<'a>(mut words: impl Iterator<Item = &'a str>) -> Result<Go, CommandParseError> {
+    fn value<'a, T: FromStr<Err = ParseIntError>>(
+        name: &str,
+        words: &mut impl Iterator<Item = &'a str>,
+    ) -> Result<T, CommandParseError> {
+        let Some(word) = words.next() else {
+            return Err(CommandParseError::MissingValue(name.to_string()));
+        };
+        word.parse()
+            .map_err(|error| CommandParseError::ValueParseError {
+                name: name.to_string(),
+                error,
+            })
+    }
+    let millis = |name, words: &mut _| value(name, words).map(Duration::from_millis);
+
+    let mut go = Go::default();
+    while let Some(name) = words.next() {
+        match name {
+            "depth" => go.depth = Some(value(name, &mut words)?),
+            "nodes" => go.nodes = Some(value(name, &mut words)?),
+            "movetime" => go.movetime = Some(millis(name, &mut words)?),
+            "wtime" => go.wtime = Some(millis(name, &mut words)?),
+            "btime" => go.btime = Some(millis(name, &mut words)?),
+            "winc" => go.winc = Some(millis(name, &mut words)?),
+            "binc" => go.binc = Some(millis(name, &mut words)?),
+            "movestogo" => go.movestogo = Some(value(name, &mut words)?),
+            "infinite" => go.infinite = true,
+            "perft" => go.perft = Some(value(name, &mut words)?),
+            _ => return Err(CommandParseError::UnknownGoParameter(name.to_string())),
+        }
+    }
+    Ok(go)
+}
+
+fn parse_setoption<'a>(
+    mut words: impl Iterator<Item = &'a str>,
+) -> Result<Command, CommandParseError> {
+    if words.next() != Some("name") {
+        return Err(CommandParseError::MissingOptionName);
+    }
+    let name = words
+        .by_ref()
+        .take_while(|&word| word != "value")
+        .collect::<Vec<_>>();
+    if name.is_empty() {
+        return Err(CommandParseError::MissingOptionName);
+    }
+    Ok(Command::SetOption {
+        name: name.join(" "),
+        value: words.collect::<Vec<_>>().join(" "),
+    })
+}
+
+/// Formats a score as it appears in an `info` line: either `cp <score>`,
+/// or `mate <moves>` for a forced win, negative if the engine is losing.
+#[must_use]
+pub fn format_score(score: i32) -> String {
+    match plies_to_win(score) {
+        Some(plies) if plies > 0 => format!("mate {}", (plies + 1) / 2),
+        Some(plies) => format!("mate -{}", (-plies + 1) / 2),
+        None => format!("cp {score}"),
+    }
+}
+
+/// Formats the result of a search iteration as an `info` line.
+#[must_use]
+pub fn format_info(result: &SearchResult) -> String {
+    let millis = result.time.as_millis();
+    let nps = u128::from(result.nodes) * 1000 / millis.max(1);
+    let mut info = format!(
+        "info depth {} score {} nodes {} nps {nps} time {millis}",
+        result.depth,
+        format_score(result.score),
+        result.nodes,
+    );
+    if !result.pv.is_empty() {
+        let pv = result
+            .pv
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        info = format!("{info} pv {}", pv.join(" "));
+    }
+    info
+}
+
+/// Writes a line to the output and flushes it, so that the
+/// controller sees it at once.
+///
+/// # Panics
+///
+/// Panics if the output can't be written to, which
+/// only happens once the controller has gone away.
+fn send(output: &Mutex<impl Write>, line: &str) {
+    let mut output = output.lock().expect("output lock poisoned");
+    writeln!(output, "{line}").expect("failed to write to output");
+    output.flush().expect("failed to flush output");
+}
+
+/// An engine that reads commands and writes replies to `W`.
+#[derive(Debug)]
+pub struct Engine<W> {
+    board: Board,
+    /// The searcher, or `None` while it's searching in the background.
+    searcher: Option<Searcher>,
+    search: Option<JoinHandle<Searcher>>,
+    /// The searcher's stop flag, which stays reachable while it's searching.
+    stop: Arc<AtomicBool>,
+    weights: Weights,
+    output: Arc<Mutex<W>>,
+}
+
+impl<W: Write + Send + 'static> Engine<W> {
+    /// Creates an engine at the starting position, writing its replies to `output`.
+    #[must_use]
+    pub fn new(output: W) -> Self {
+        let searcher = Searcher::new(DEFAULT_HASH_MEGABYTES);
+        Self {
+            board: Board::default(),
+            stop: searcher.stop_flag(),
+            searcher: Some(searcher),
+            search: None,
+            weights: Weights::default(),
+            output: Arc::new(Mutex::new(output)),
+        }
+    }
+
+    /// Returns the position that the next search will start from.
+    #[must_use]
+    pub const fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Handles commands from `input` until it ends, can't be read from,
+    /// or sends a `quit` command. Any search in progress is then stopped.
+    /// Lines that aren't UTF-8 are reported and skipped.
+    pub fn run(&mut self, input: impl BufRead) {
+        for line in input.split(b'\n') {
+            let Ok(mut line) = line else {
+                break;
+            };
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            let Ok(line) = String::from_utf8(line) else {
+                self.send("info string invalid command: not UTF-8");
+                continue;
+            };
+            if !self.handle_line(&line) {
+                break;
+            }
+        }
+        self.stop();
+    }
+
+    /// Handles a line of input. Lines that aren't valid commands are
+    /// reported with an `info string` line, and otherwise ignored.
+    /// Returns `false` once the engine should quit.
+    pub fn handle_line(&mut self, line: &str) -> bool {
+        match line.parse() {
+            Ok(command) => self.handle(command),
+            Err(CommandParseError::Empty) => true,
+            Err(error) => {
+                self.send(&format!("info string invalid command: {error:?}"));
+                true
+            }
+        }
+    }
+
+    /// Handles a command. Returns `false` once the engine should quit.
+    /// Commands that change the engine's state stop any search in progress.
+    pub fn handle(&mut self, command: Command) -> bool {
+        match command {
+            Command::Uci => self.identify(),
+            Command::IsReady => self.send("readyok"),
+            Command::NewGame => {
+                self.stop();
+                self.idle_searcher().clear();
+            }
+            Command::Position { board, moves } => {
+                self.stop();
+                self.set_position(board, &moves);
+            }
+            Command::Go(go) => self.go(&go),
+            Command::Stop => self.stop(),
+            Command::SetOption { name, value } => {
+                self.stop();
+                self.set_option(&name, &value);
+            }
+            Command::Quit => {
+                self.stop();
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Waits for the search in progress, if any, to finish by itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the search thread panicked.
+    pub fn wait(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.searcher = Some(search.join().expect("search thread panicked"));
+        }
+    }
+
+    /// Stops the search in progress, if any, and waits for it to report its move.
+    pub fn stop(&mut self) {
+        if let Some(search) = &self.search {
+            // the searcher clears its flag as it starts, so the flag is set
+            // again until the search ends, in case it had yet to start.
+            while !search.is_finished() {
+                self.stop.store(true, Ordering::Relaxed);
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            self.wait();
+        }
+    }
+
+    fn send(&self, line: &str) {
+        send(&self.output, line);
+    }
+
+    const fn idle_searcher(&mut self) -> &mut Searcher {
+        self.searcher
+            .as_mut()
+            .expect("the searcher is only away while searching")
+    }
+
+    fn identify(&self) {
+        self.send(concat!("id name quorigen ", env!("CARGO_PKG_VERSION")));
+        self.send("id author the quorigen developers");
+        self.send(&format!(
+            "option name Hash type spin default {DEFAULT_HASH_MEGABYTES} min {} max {}",
+            HASH_RANGE.start(),
+            HASH_RANGE.end()
+        ));
+        self.send("option name Weights type string default <empty>");
+        let defaults = Weights::default();
+        for name in Weights::NAMES {
+            let default = defaults.get(name).expect("every name has a weight");
+            self.send(&format!(
+                "option name {name} type spin default {default} min {} max {}",
+                WEIGHT_RANGE.start(),
+                WEIGHT_RANGE.end()
+            ));
+        }
+        self.send("uciok");
+    }
+
+    /// Sets the position to `board` after `moves`. If any of the moves are
+    /// illegal, the position is left as it was.
+    fn set_position(&mut self, mut board: Board, moves: &[Move]) {
+        for &mv in moves {
+            if let Err(error) = board.try_make_move(mv) {
+                self.send(&format!("info string illegal move {mv}: {error:?}"));
+                return;
+            }
+        }
+        self.board = board;
+    }
+
+    fn go(&mut self, go: &Go) {
+        self.stop();
+        if let Some(depth) = go.perft {
+            self.perft(depth);
+            return;
+        }
+
+        let board = self.board;
+        let limits = go.limits(board.turn());
+        let mut searcher = self
+            .searcher
+            .take()
+            .expect("the searcher is only away while searching");
+        let output = Arc::clone(&self.output);
+        self.search = Some(std::thread::spawn(move || {
+            let result = searcher.search_with_progress(&board, &limits, |result| {
+                send(&output, &format_info(result));
+            });
+            let best_move = result
+                .best_move
+                .map_or_else(|| "(none)".to_string(), |mv| mv.to_string());
+            send(&output, &format!("bestmove {best_move}"));
+            searcher
+        }));
+    }
+
+    fn perft(&self, depth: u8) {
+        let start = Instant::now();
+        let divide = perft::divide(self.board, depth);
+        for (mv, count) in &divide {
+            self.send(&format!("{mv}: {count}"));
+        }
+        let nodes = if depth == 0 {
+            1
+        } else {
+            divide.iter().map(|(_, count)| count).sum()
+        };
+        let millis = start.elapsed().as_millis();
+        self.send(&format!("info nodes {nodes} time {millis}"));
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) {
+        let name = name.to_ascii_lowercase();
+        match name.as_str() {
+            "hash" => match value.parse() {
+                Ok(megabytes) if HASH_RANGE.contains(&megabytes) => {
+                    let mut searcher = Searcher::new(megabytes);
+                    searcher.set_weights(self.weights);
+                    self.stop = searcher.stop_flag();
+                    self.searcher = Some(searcher);
+                }
+                Ok(megabytes) => {
+                    self.send(&format!(
+                        "info string hash size must be between {} and {}, not {megabytes}",
+                        HASH_RANGE.start(),
+                        HASH_RANGE.end()
+                    ));
+                }
+                Err(error) => {
+                    self.send(&format!("info string invalid hash size {value}: {error:?}"));
+                }
+            },
+            "weights" => match Weights::load(value) {
+                Ok(weights) => {
+                    let out_of_range = Weights::NAMES.into_iter().find(|name| {
+                        !WEIGHT_RANGE.contains(&weights.get(name).expect("every name has a weight"))
+                    });
+                    match out_of_range {
+                        Some(name) => self.send(&format!(
+                            "info string {name} must be between {} and {}",
+                            WEIGHT_RANGE.start(),
+                            WEIGHT_RANGE.end()
+                        )),
+                        None => self.set_weights(weights),
+                    }
+                }
+                Err(error) => self.send(&format!("info string failed to load weights: {error:?}")),
+            },
+            _ => {
+                let mut weights = self.weights;
+                let Some(weight) = weights.get_mut(&name) else {
+                    self.send(&format!("info string unknown option {name}"));
+                    return;
+                };
+                match value.parse() {
+                    Ok(value) if WEIGHT_RANGE.contains(&value) => {
+                        *weight = value;
+                        self.set_weights(weights);
+                    }
+                    Ok(value) => {
+                        self.send(&format!(
+                            "info string {name} must be between {} and {}, not {value}",
+                            WEIGHT_RANGE.start(),
+                            WEIGHT_RANGE.end()
+                        ));
+                    }
+                    Err(error) => {
+                        self.send(&format!("info string invalid value {value}: {error:?}"));
+                    }
+                }
+            }
+        }
+    }
+
+    const fn set_weights(&mut self, weights: Weights) {
+        self.weights = weights;
+        self.idle_searcher().set_weights(weights);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::{format_score, Command, CommandParseError, Engine, Go};
+    use crate::{
+        board::Board,
+        search::WIN,
+        types::{Colour, MoveParseError},
+    };
+
+    /// An output that can still be read after the engine has taken it.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedBuffer {
+        fn lines(&self) -> Vec<String> {
+            let text = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+            text.lines().map(str::to_string).collect()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn parse_commands() {
+        let mv = |s: &str| s.parse().unwrap();
+        assert_eq!(
+            "position startpos moves e2 e8 e4h".parse(),
+            Ok(Command::Position {
+                board: Board::default(),
+                moves: vec![mv("e2"), mv("e8"), mv("e4h")],
+            })
+        );
+        assert_eq!(
+            "position fen d4 /  / e5 e6 / 9 10 / 2".parse(),
+            Ok(Command::Position {
+                board: "d4 /  / e5 e6 / 9 10 / 2".parse().unwrap(),
+                moves: Vec::new(),
+            })
+        );
+        assert_eq!(
+            "position fen / / e1 e9 / 10 10 / 1 moves e2".parse(),
+            Ok(Command::Position {
+                board: Board::default(),
+                moves: vec![mv("e2")],
+            })
+        );
+        assert_eq!(
+            "go wtime 70000 btime 50000 winc 1000 depth 9".parse(),
+            Ok(Command::Go(Go {
+                depth: Some(9),
+                wtime: Some(Duration::from_secs(70)),
+                btime: Some(Duration::from_secs(50)),
+                winc: Some(Duration::from_secs(1)),
+                ..Go::default()
+            }))
+        );
+        assert_eq!(
+            "setoption name Weights value /tmp/my weights.txt".parse(),
+            Ok(Command::SetOption {
+                name: "Weights".to_string(),
+                value: "/tmp/my weights.txt".to_string(),
+            })
+        );
+        assert_eq!("  ".parse::<Command>(), Err(CommandParseError::Empty));
+        assert_eq!(
+            "position e1".parse::<Command>(),
+            Err(CommandParseError::MissingPosition)
+        );
+        assert_eq!(
+            "go depth".parse::<Command>(),
+            Err(CommandParseError::MissingValue("depth".to_string()))
+        );
+        assert!(matches!(
+            "position startpos moves e2 z9".parse::<Command>(),
+            Err(CommandParseError::Move(_))
+        ));
+        assert_eq!(
+            "position startpos moves aé".parse::<Command>(),
+            Err(CommandParseError::Move(MoveParseError::NonAscii))
+        );
+        assert!(matches!(
+            "go movetime soon".parse::<Command>(),
+            Err(CommandParseError::ValueParseError { .. })
+        ));
+    }
+
+    #[test]
+    fn time_management() {
+        let go = Go {
+            wtime: Some(Duration::from_secs(50)),
+            btime: Some(Duration::from_millis(40)),
+            winc: Some(Duration::from_secs(2)),
+            ..Go::default()
+        };
+        assert_eq!(go.limits(Colour::White).time, Some(Duration::from_secs(3)));
+        // never more than is left on the clock, less the overhead.
+        assert_eq!(
+            go.limits(Colour::Black).time,
+            Some(Duration::from_millis(1))
+        );
+        let go = Go {
+            movetime: Some(Duration::from_millis(300)),
+            nodes: Some(1000),
+            ..go
+        };
+        assert_eq!(
+            go.limits(Colour::Black).time,
+            Some(Duration::from_millis(300))
+        );
+        assert_eq!(go.limits(Colour::Black).nodes, Some(1000));
+        let go = Go {
+            infinite: true,
+            ..go
+        };
+        assert_eq!(go.limits(Colour::White), crate::search::Limits::default());
+
+        // with only the opponent's clock, the search still has a time limit.
+        let go = Go {
+            wtime: Some(Duration::from_secs(25)),
+            ..Go::default()
+        };
+        assert_eq!(go.limits(Colour::Black).time, Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn scores() {
+        assert_eq!(format_score(-35), "cp -35");
+        assert_eq!(format_score(WIN - 1), "mate 1");
+        assert_eq!(format_score(WIN - 3), "mate 2");
+        assert_eq!(format_score(-(WIN - 2)), "mate -1");
+    }
+
+    #[test]
+    fn run_skips_lines_that_are_not_utf8() {
+        let output = SharedBuffer::default();
+        let mut engine = Engine::new(output.clone());
+        engine.run(&b"isready\r\n\xFF\xFE\nisready\nquit\nisready\n"[..]);
+        assert_eq!(
+            output.lines(),
+            [
+                "readyok",
+                "info string invalid command: not UTF-8",
+                "readyok"
+            ]
+        );
+    }
+
+    #[test]
+    fn session() {
+        let output = SharedBuffer::default();
+        let mut engine = Engine::new(output.clone());
+        let input = "uci\nisready\nsetoption name Hash value 1\nsetoption name tempo value 15\n\
+                     position startpos moves e2 e8 e4h\ngo depth 3\n";
+        for line in input.lines() {
+            assert!(engine.handle_line(line));
+        }
+        engine.wait();
+        let lines = output.lines();
+        assert!(lines[0].starts_with("id name quorigen"));
+        assert!(lines.contains(&"uciok".to_string()));
+        assert!(lines.contains(&"readyok".to_string()));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("info depth 3 score cp ")));
+        let best_move = lines.last().unwrap().strip_prefix("bestmove ").unwrap();
+        assert!(engine.board().is_legal(best_move.parse().unwrap()));
+        assert_eq!(engine.board().ply(), 3);
+
+        // an illegal move leaves the position alone, and bad input is reported.
+        engine.handle_line("position startpos moves e2 e2");
+        engine.handle_line("go sideways");
+        engine.handle_line("setoption name Threads value 4");
+        engine.handle_line("position startpos moves aé");
+        engine.handle_line("setoption name tempo value 20000");
+        engine.handle_line("setoption name Hash value 99999999999");
+        engine.handle_line("setoption name Hash value 0");
+        let lines = output.lines();
+        let reports = &lines[lines.len() - 7..];
+        assert!(reports.iter().all(|line| line.starts_with("info string")));
+        assert_eq!(
+            lines.last().unwrap(),
+            "info string hash size must be between 1 and 65536, not 0"
+        );
+        assert_eq!(engine.board().ply(), 3);
+
+        // an infinite search only ends when told to.
+        engine.handle_line("go infinite");
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!engine.handle_line("quit"));
+        assert!(output.lines().last().unwrap().starts_with("bestmove "));
+
+        engine.handle_line("position fen / / e8 a9 / 0 0 / 1");
+        engine.handle_line("go perft 1");
+        let lines = output.lines();
+        assert!(lines.contains(&"e9: 1".to_string()));
+        assert!(lines.last().unwrap().starts_with("info nodes 4 "));
+    }
+}
@@ -0,0 +1,592 @@
+//! Records of whole games, in a format modelled on PGN.
+//!
+//! A record is a list of tag pairs followed by the moves of the game, with
+//! move numbers, comments in braces, and variations in parentheses, ending
+//! with the result:
+//!
+//! ```text
+//! [Event "Club championship"]
+//! [White "Alice"]
+//! [Black "Bob"]
+//! [Result "1-0"]
+//!
+//! 1. e2 e8 2. e3 e4h {blocks white's path} (2... e7 3. e4) 3. d3 ...
+//! ... 1-0
+//! ```
+//!
+//! The result is `1-0` if white won, `0-1` if black won, and `*` if the game
+//! is unfinished. A game needn't end with a pawn on its goal row, as players
+//! may resign or lose on time. Games that don't start from the usual starting
+//! position have a `FEN` tag. A file may hold any number of records, one
+//! after another.
+
+use std::{fmt::Display, iter::Peekable, str::FromStr};
+
+use crate::{
+    board::{Board, FenParseError, IllegalMoveError},
+    types::{Colour, GameResult, Move, MoveParseError},
+};
+
+/// A game, along with information about where and by whom it was played.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GameRecord {
+    /// The tag pairs describing the game, in order, other than
+    /// `Result` and `FEN`, which are held in their own fields.
+    pub tags: Vec<(String, String)>,
+    /// The position the game started from.
+    pub start: Board,
+    /// The moves of the game.
+    pub mainline: Variation,
+    /// The result of the game, or `None` if it's unfinished.
+    pub result: Option<GameResult>,
+}
+
+/// A sequence of moves, with an optional comment before the first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Variation {
+    pub comment: Option<String>,
+    pub moves: Vec<RecordedMove>,
+}
+
+/// A move of a game, with any comment on it and any alternatives to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedMove {
+    pub mv: Move,
+    /// A comment on the move.
+    pub comment: Option<String>,
+    /// Lines that could have been played instead of this move.
+    pub variations: Vec<Variation>,
+}
+
+impl RecordedMove {
+    /// Creates a recorded move with no comment or variations.
+    #[must_use]
+    pub const fn new(mv: Move) -> Self {
+        Self {
+            mv,
+            comment: None,
+            variations: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordParseError {
+    MalformedTag(String),
+    Fen(FenParseError),
+    UnterminatedComment,
+    Move(MoveParseError),
+    /// A move that can't be played, along with the ply at which it was found.
+    IllegalMove {
+        ply: u16,
+        mv: Move,
+        error: IllegalMoveError,
+    },
+    VariationBeforeMove,
+    UnbalancedVariation,
+    UnexpectedTag(String),
+    MissingResult,
+    /// The result given doesn't match the one given by the tags or the final position.
+    ResultMismatch {
+        expected: Option<GameResult>,
+        found: Option<GameResult>,
+    },
+    InvalidResult(String),
+}
+
+impl GameRecord {
+    /// Creates a record of a game from `start` with no moves or tags.
+    #[must_use]
+    pub fn new(start: Board) -> Self {
+        Self {
+            start,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the value of the tag with the given name.
+    #[must_use]
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value of the tag with the given name, replacing any existing value.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, existing)) => *existing = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Returns the position at the end of the main line.
+    #[must_use]
+    pub fn final_board(&self) -> Board {
+        let mut board = self.start;
+        for recorded in &self.mainline.moves {
+            board.make_move(recorded.mv);
+        }
+        board
+    }
+
+    /// Appends a move to the main line. If it ends the game,
+    /// the result is set to match.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the move is illegal at the end of the main line,
+    /// in which case the record is left untouched.
+    pub fn push(&mut self, mv: Move) -> Result<(), IllegalMoveError> {
+        let mut board = self.final_board();
+        board.try_make_move(mv)?;
+        self.mainline.moves.push(RecordedMove::new(mv));
+        if let Some(outcome) = board.outcome() {
+            self.result = Some(outcome);
+        }
+        Ok(())
+    }
+}
+
+/// Parses every game record in `text`, one after another.
+///
+/// # Errors
+///
+/// Returns the first error found in any of the records.
+pub fn parse_games(text: &str) -> Result<Vec<GameRecord>, RecordParseError> {
+    let mut tokens = Lexer { text }.peekable();
+    let mut games = Vec::new();
+    while tokens.peek().is_some() {
+        games.push(parse_game(&mut tokens)?);
+    }
+    Ok(games)
+}
+
+impl FromStr for GameRecord {
+    type Err = RecordParseError;
+
+    /// Parses a single game record. Anything after the result is ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_game(&mut Lexer { text: s }.peekable())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token<'a> {
+    Tag { name: String, value: String },
+    Comment(&'a str),
+    OpenVariation,
+    CloseVariation,
+    Result(&'a str),
+    Move(&'a str),
+}
+
+/// Splits a record into tokens, skipping move numbers.
+struct Lexer<'a> {
+    text: &'a str,
+}
+
+impl<'a> Lexer<'a> {
+    /// Lexes a tag pair, of the form `[Name "value"]`, where the value may
+    /// contain escaped quotes and backslashes.
+    fn tag(&mut self) -> Result<Token<'a>, RecordParseError> {
+        let end = self.text.find(']').unwrap_or(self.text.len());
+        let malformed = || RecordParseError::MalformedTag(self.text[..end].to_string());
+        let (name, rest) = self.text[1..]
+            .trim_start()
+            .split_once('"')
+            .ok_or_else(malformed)?;
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(malformed());
+        }
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let close = loop {
+            match chars.next() {
+                Some((_, '\\')) => value.push(chars.next().ok_or_else(malformed)?.1),
+                Some((index, '"')) => break index,
+                Some((_, c)) => value.push(c),
+                None => return Err(malformed()),
+            }
+        };
+        let rest = rest[close + 1..].trim_start();
+        let Some(rest) = rest.strip_prefix(']') else {
+            return Err(malformed());
+        };
+        self.text = rest;
+        Ok(Token::Tag {
+            name: name.to_string(),
+            value,
+        })
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>, RecordParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.text = self.text.trim_start();
+            let token = match self.text.chars().next()? {
+                '[' => return Some(self.tag()),
+                '{' => {
+                    let Some(end) = self.text.find('}') else {
+                        return Some(Err(RecordParseError::UnterminatedComment));
+                    };
+                    let comment = self.text[1..end].trim();
+                    self.text = &self.text[end + 1..];
+                    return Some(Ok(Token::Comment(comment)));
+                }
+                '(' => Token::OpenVariation,
+                ')' => Token::CloseVariation,
+                _ => {
+                    let end = self
+                        .text
+                        .find(|c: char| c.is_whitespace() || "[]{}()".contains(c))
+                        .unwrap_or(self.text.len());
+                    let word = &self.text[..end];
+                    self.text = &self.text[end..];
+                    if matches!(word, "1-0" | "0-1" | "*") {
+                        return Some(Ok(Token::Result(word)));
+                    }
+                    // move numbers, such as "12." or "12...", may run
+                    // straight into the move that follows, as in "1.e2".
+                    let unnumbered = word.trim_start_matches(|c: char| c.is_ascii_digit());
+                    let word = if unnumbered.len() < word.len() && unnumbered.starts_with('.') {
+                        unnumbered.trim_start_matches('.')
+                    } else {
+                        word
+                    };
+                    if word.is_empty() {
+                        continue;
+                    }
+                    return Some(Ok(Token::Move(word)));
+                }
+            };
+            self.text = &self.text[1..];
+            return Some(Ok(token));
+        }
+    }
+}
+
+fn parse_result(text: &str) -> Result<Option<GameResult>, RecordParseError> {
+    match text {
+        "1-0" => Ok(Some(GameResult::WhiteWin)),
+        "0-1" => Ok(Some(GameResult::BlackWin)),
+        "*" => Ok(None),
+        _ => Err(RecordParseError::InvalidResult(text.to_string())),
+    }
+}
+
+fn parse_game<'a>(
+    tokens: &mut Peekable<impl Iterator<Item = Result<Token<'a>, RecordParseError>>>,
+) -> Result<GameRecord, RecordParseError> {
+    let mut record = GameRecord::default();
+    let mut tagged_result = None;
+    while let Some(Ok(Token::Tag { .. })) = tokens.peek() {
+        let Some(Ok(Token::Tag { name, value })) = tokens.next() else {
+            unreachable!("the token was just peeked");
+        };
+        match name.as_str() {
+            "FEN" => record.start = value.parse().map_err(RecordParseError::Fen)?,
+            "Result" => tagged_result = Some(parse_result(&value)?),
+            _ => record.tags.push((name, value)),
+        }
+    }
+
+    let mut board = record.start;
+    let result = parse_variation(tokens, &mut board, &mut record.mainline, false)?;
+    // a finished game has to have been won by whoever reached their goal row.
+    let expected = board.outcome().map(Some).or(tagged_result);
+    if let Some(expected) = expected.filter(|&expected| expected != result) {
+        return Err(RecordParseError::ResultMismatch {
+            expected,
+            found: result,
+        });
+    }
+    record.result = result;
+    Ok(record)
+}
+
+/// Parses moves into `variation`, playing them on `board`, until the end of
+/// the variation if `nested`, or else until the result, which is returned.
+fn parse_variation<'a>(
+    tokens: &mut Peekable<impl Iterator<Item = Result<Token<'a>, RecordParseError>>>,
+    board: &mut Board,
+    variation: &mut Variation,
+    nested: bool,
+) -> Result<Option<GameResult>, RecordParseError> {
+    // the position before the last move, from which its alternatives are played.
+    let mut before = *board;
+    loop {
+        let Some(token) = tokens.next() else {
+            return Err(if nested {
+                RecordParseError::UnbalancedVariation
+            } else {
+                RecordParseError::MissingResult
+            });
+        };
+        match token? {
+            Token::Tag { name, .. } => return Err(RecordParseError::UnexpectedTag(name)),
+            Token::Comment(comment) => {
+                let target = match variation.moves.last_mut() {
+                    Some(last) => &mut last.comment,
+                    None => &mut variation.comment,
+                };
+                match target {
+                    Some(existing) => {
+                        existing.push(' ');
+                        existing.push_str(comment);
+                    }
+                    None => *target = Some(comment.to_string()),
+                }
+            }
+            Token::OpenVariation => {
+                let Some(last) = variation.moves.last_mut() else {
+                    return Err(RecordParseError::VariationBeforeMove);
+                };
+                let mut alternative = Variation::default();
+                let mut board = before;
+                parse_variation(tokens, &mut board, &mut alternative, true)?;
+                last.variations.push(alternative);
+            }
+            Token::CloseVariation if nested => return Ok(None),
+            Token::CloseVariation => return Err(RecordParseError::UnbalancedVariation),
+            Token::Result(_) if nested => return Err(RecordParseError::UnbalancedVariation),
+            Token::Result(result) => return parse_result(result),
+            Token::Move(text) => {
+                let mv = text.parse().map_err(RecordParseError::Move)?;
+                before = *board;
+                board
+                    .try_make_move(mv)
+                    .map_err(|error| RecordParseError::IllegalMove {
+                        ply: before.ply(),
+                        mv,
+                        error,
+                    })?;
+                variation.moves.push(RecordedMove::new(mv));
+            }
+        }
+    }
+}
+
+/// The column at which movetext is wrapped onto a new line.
+const LINE_WIDTH: usize = 80;
+
+/// Appends the words of the movetext of a variation played from `board` to
+/// `words`. Move numbers are written before white's moves, and before black's
+/// at the start of a variation or after anything that interrupts the moves.
+fn variation_words(mut board: Board, variation: &Variation, words: &mut Vec<String>) {
+    if let Some(comment) = &variation.comment {
+        words.push(format!("{{{comment}}}"));
+    }
+    let mut interrupted = true;
+    for recorded in &variation.moves {
+        let number = board.ply() / 2 + 1;
+        words.push(if board.turn() == Colour::White {
+            format!("{number}. {}", recorded.mv)
+        } else if interrupted {
+            format!("{number}... {}", recorded.mv)
+        } else {
+            recorded.mv.to_string()
+        });
+        if let Some(comment) = &recorded.comment {
+            words.push(format!("{{{comment}}}"));
+        }
+        for alternative in &recorded.variations {
+            let first = words.len();
+            variation_words(board, alternative, words);
+            if words.len() == first {
+                words.push("()".to_string());
+            } else {
+                words[first].insert(0, '(');
+                words
+                    .last_mut()
+                    .expect("the variation isn't empty")
+                    .push(')');
+            }
+        }
+        interrupted = recorded.comment.is_some() || !recorded.variations.is_empty();
+        board.make_move(recorded.mv);
+    }
+}
+
+impl Display for GameRecord {
+    /// Writes the record in the format read by [`GameRecord::from_str`].
+    /// Values are escaped as needed, but comments mustn't contain `}`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let result = match self.result {
+            Some(GameResult::WhiteWin) => "1-0",
+            Some(GameResult::BlackWin) => "0-1",
+            None => "*",
+        };
+        let start = self.start.to_string();
+        let mut tags = self
+            .tags
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        tags.push(("Result", result));
+        if self.start != Board::default() {
+            tags.push(("FEN", &start));
+        }
+        for (name, value) in tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(f, "[{name} \"{value}\"]")?;
+        }
+        writeln!(f)?;
+
+        let mut words = Vec::new();
+        variation_words(self.start, &self.mainline, &mut words);
+        words.push(result.to_string());
+        let mut line_length = 0;
+        for word in words {
+            if line_length > 0 && line_length + 1 + word.len() > LINE_WIDTH {
+                writeln!(f)?;
+                line_length = 0;
+            } else if line_length > 0 {
+                write!(f, " ")?;
+                line_length += 1;
+            }
+            write!(f, "{word}")?;
+            line_length += word.len();
+        }
+        writeln!(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_games, GameRecord, RecordParseError};
+    use crate::{
+        board::IllegalMoveError,
+        types::{GameResult, MoveParseError},
+    };
+
+    #[test]
+    fn round_trip() {
+        let text = r#"[Event "Test \"quoted\" \\ slashed"]
+[White "A"]
+[Black "B"]
+[Result "*"]
+
+{opening} 1. e2 e8 2. e3 {solid} (2. e4h {a wall} 2... e7 (2... d8) 3. e3)
+2... e4h 3. d3 *
+"#;
+        let record: GameRecord = text.parse().unwrap();
+        assert_eq!(record.tag("Event"), Some(r#"Test "quoted" \ slashed"#));
+        assert_eq!(record.tag("Result"), None);
+        assert_eq!(record.result, None);
+        assert_eq!(record.mainline.comment.as_deref(), Some("opening"));
+        let moves = &record.mainline.moves;
+        assert_eq!(moves.len(), 5);
+        assert_eq!(moves[2].comment.as_deref(), Some("solid"));
+        let alternative = &moves[2].variations[0];
+        assert_eq!(alternative.moves.len(), 3);
+        assert_eq!(
+            alternative.moves[1].variations[0].moves[0].mv.to_string(),
+            "d8"
+        );
+        assert_eq!(record.final_board().to_string(), "e4 /  / d3 e8 / 10 9 / 2");
+        assert_eq!(record.to_string(), text);
+    }
+
+    #[test]
+    fn several_games() {
+        let text = "[White \"A\"]\n[FEN \" /  / e5 a9 / 10 10 / 1\"]\n\n\
+                    1.e6 a8 2.e7 a7 3.e8 a6 4.e9 1-0\n\n\
+                    [White \"B\"]\n[Result \"0-1\"]\n\n1. e2 0-1\n";
+        let games = parse_games(text).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].result, Some(GameResult::WhiteWin));
+        assert_eq!(games[0].mainline.moves.len(), 7);
+        // black resigned, or lost on time.
+        assert_eq!(games[1].result, Some(GameResult::BlackWin));
+        let written = games
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(parse_games(&written).unwrap(), games);
+        assert!(written.starts_with("[White \"A\"]\n[Result \"1-0\"]\n[FEN "));
+    }
+
+    #[test]
+    fn black_to_move_first() {
+        let mut record = GameRecord::new(" /  / e5 e7 / 10 10 / 2".parse().unwrap());
+        record.set_tag("Event", "Endgame study");
+        for mv in ["e6", "e4", "f6", "e3"] {
+            record.push(mv.parse().unwrap()).unwrap();
+        }
+        assert_eq!(
+            record.push("a1".parse().unwrap()),
+            Err(IllegalMoveError::PawnNotReachable)
+        );
+        let text = record.to_string();
+        assert!(text.ends_with("\n1... e6 2. e4 f6 3. e3 *\n"), "{text}");
+        assert_eq!(text.parse::<GameRecord>(), Ok(record));
+    }
+
+    #[test]
+    fn errors() {
+        let parse = |text: &str| text.parse::<GameRecord>();
+        assert_eq!(
+            parse("1. e2 e8 2. e4 *"),
+            Err(RecordParseError::IllegalMove {
+                ply: 2,
+                mv: "e4".parse().unwrap(),
+                error: IllegalMoveError::PawnNotReachable,
+            })
+        );
+        assert_eq!(
+            parse("1. e2 (1. d1 *"),
+            Err(RecordParseError::UnbalancedVariation)
+        );
+        assert_eq!(
+            parse("1. e2 e8) *"),
+            Err(RecordParseError::UnbalancedVariation)
+        );
+        assert_eq!(
+            parse("(1. d1) 1. e2 *"),
+            Err(RecordParseError::VariationBeforeMove)
+        );
+        assert_eq!(parse("1. e2 e8"), Err(RecordParseError::MissingResult));
+        assert_eq!(
+            parse("1. e2 {never closed *"),
+            Err(RecordParseError::UnterminatedComment)
+        );
+        assert_eq!(
+            parse("[White A]\n1. e2 *"),
+            Err(RecordParseError::MalformedTag("[White A".to_string()))
+        );
+        assert_eq!(
+            parse("[Result \"0-1\"]\n1. e2 1-0"),
+            Err(RecordParseError::ResultMismatch {
+                expected: Some(GameResult::BlackWin),
+                found: Some(GameResult::WhiteWin),
+            })
+        );
+        // a pawn on its goal row decides the result, whatever the tags say.
+        assert_eq!(
+            parse("[FEN \" /  / e8 a9 / 0 0 / 1\"]\n1. e9 *"),
+            Err(RecordParseError::ResultMismatch {
+                expected: Some(GameResult::WhiteWin),
+                found: None,
+            })
+        );
+        assert!(matches!(
+            parse("1. e2 e8x *"),
+            Err(RecordParseError::Move(_))
+        ));
+        assert_eq!(
+            parse("1. aé *"),
+            Err(RecordParseError::Move(MoveParseError::NonAscii))
+        );
+        assert!(matches!(
+            parse("[FEN \"nonsense\"] *"),
+            Err(RecordParseError::Fen(_))
+        ));
+    }
+}
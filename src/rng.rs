@@ -0,0 +1,42 @@
+//! A small, fast, non-cryptographic pseudorandom number generator.
+
+/// A xorshift64* generator.
+#[derive(Debug, Clone)]
+pub struct XorShiftRng {
+    state: u64,
+}
+
+impl XorShiftRng {
+    /// Creates a new generator from the given seed.
+    /// The state of a xorshift generator must never be zero,
+    /// so a zero seed is replaced with a fixed nonzero constant.
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        Self {
+            state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed },
+        }
+    }
+
+    /// Returns the next pseudorandom `u64`.
+    pub const fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a pseudorandom number in `0..upper`.
+    /// `upper` must be nonzero.
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn next_below(&mut self, upper: usize) -> usize {
+        // multiply-shift range reduction, which avoids a division.
+        ((self.next_u64() as u128 * upper as u128) >> 64) as usize
+    }
+
+    /// Returns a pseudorandom number in `0.0..1.0`.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn next_f64(&mut self) -> f64 {
+        // the top 53 bits, which is all the precision an f64 has.
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,586 @@
+//! Alpha-beta search.
+//!
+//! The search is a negamax alpha-beta search with principal variation search,
+//! driven by iterative deepening with aspiration windows. Moves are ordered by
+//! the transposition table, killer moves and history scores, via [`MovePicker`].
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::Board,
+    eval::{self, Weights},
+    movelist::MoveList,
+    movepick::{History, MovePicker, Stage},
+    tt::{TableEntry, TranspositionTable},
+    types::Move,
+};
+
+/// The score of a won position, less the number of plies it takes to win.
+pub const WIN: i32 = 30_000;
+/// A score beyond that of any position.
+pub const INFINITY: i32 = 32_000;
+/// The deepest the search will ever go, in plies from the root.
+pub const MAX_PLY: usize = 128;
+/// Scores beyond this are wins or losses found by the search.
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+pub(crate) const WIN_BOUND: i32 = WIN - MAX_PLY as i32;
+
+/// How often, in nodes, the search checks whether it should stop.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Returns the number of plies until the winner reaches their goal row,
+/// if `score` is a forced win or loss. Positive for wins, negative for losses.
+#[must_use]
+pub const fn plies_to_win(score: i32) -> Option<i32> {
+    if score > WIN_BOUND {
+        Some(WIN - score)
+    } else if score < -WIN_BOUND {
+        Some(-WIN - score)
+    } else {
+        None
+    }
+}
+
+/// Constraints on how long a search may run. The search stops as soon as
+/// any limit is reached, or when told to by [`Searcher::stop_flag`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The deepest iteration to complete.
+    pub depth: Option<u8>,
+    /// The most nodes to search.
+    pub nodes: Option<u64>,
+    /// The longest the search may run for.
+    pub time: Option<Duration>,
+}
+
+/// The outcome of a search, as of its last completed iteration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchResult {
+    /// The best move found, or `None` if there were no legal moves.
+    pub best_move: Option<Move>,
+    /// The score of the position from the point of view of the side to move.
+    pub score: i32,
+    /// The depth of the last completed iteration.
+    pub depth: u8,
+    /// The number of positions visited.
+    pub nodes: u64,
+    /// The time taken.
+    pub time: Duration,
+    /// The expected line of play, starting with the best move.
+    pub pv: Vec<Move>,
+}
+
+/// How a stored score relates to the true score of its position.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Bound {
+    /// The true score is at most the stored score.
+    Upper,
+    /// The true score is at least the stored score.
+    Lower,
+    /// The stored score is the true score.
+    Exact,
+}
+
+/// A search result stored in the transposition table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SearchEntry {
+    best_move: Option<Move>,
+    score: i16,
+    depth: u8,
+    bound: Bound,
+}
+
+impl TableEntry for SearchEntry {
+    fn pack(self) -> u64 {
+        let best_move = self.best_move.map_or(0, Move::to_u16);
+        let score = self.score.cast_unsigned();
+        let bound = match self.bound {
+            Bound::Upper => 0,
+            Bound::Lower => 1,
+            Bound::Exact => 2,
+        };
+        u64::from(best_move) | u64::from(score) << 16 | u64::from(self.depth) << 32 | bound << 40
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn unpack(data: u64) -> Self {
+        Self {
+            best_move: Move::from_u16(data as u16),
+            score: ((data >> 16) as u16).cast_signed(),
+            depth: (data >> 32) as u8,
+            bound: match (data >> 40) & 0b11 {
+                0 => Bound::Upper,
+                1 => Bound::Lower,
+                _ => Bound::Exact,
+            },
+        }
+    }
+
+    fn depth(self) -> u8 {
+        self.depth
+    }
+}
+
+/// Converts a score relative to the root into one relative to the
+/// position at `ply`, so that wins are stored as distances from the
+/// position rather than from whichever root it was first searched from.
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+const fn score_to_table(score: i32, ply: usize) -> i16 {
+    let ply = ply as i32;
+    let score = if score > WIN_BOUND {
+        score + ply
+    } else if score < -WIN_BOUND {
+        score - ply
+    } else {
+        score
+    };
+    score as i16
+}
+
+/// The inverse of [`score_to_table`].
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+const fn score_from_table(score: i16, ply: usize) -> i32 {
+    let ply = ply as i32;
+    let score = score as i32;
+    if score > WIN_BOUND {
+        score - ply
+    } else if score < -WIN_BOUND {
+        score + ply
+    } else {
+        score
+    }
+}
+
+/// Searches positions for the best move, keeping the transposition table
+/// and history scores from one search to the next.
+#[derive(Debug)]
+pub struct Searcher {
+    table: TranspositionTable<SearchEntry>,
+    history: History,
+    weights: Weights,
+    stop: Arc<AtomicBool>,
+}
+
+impl Searcher {
+    /// Creates a searcher with a transposition table of at most `megabytes` megabytes.
+    #[must_use]
+    pub fn new(megabytes: usize) -> Self {
+        Self {
+            table: TranspositionTable::new(megabytes),
+            history: History::new(),
+            weights: Weights::default(),
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Returns a flag that stops the search in progress when set.
+    /// The flag is cleared at the start of every search.
+    #[must_use]
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    /// Sets the weights of the evaluation used at the leaves of the search.
+    pub const fn set_weights(&mut self, weights: Weights) {
+        self.weights = weights;
+    }
+
+    /// Forgets everything learned in earlier searches, as before a new game.
+    pub fn clear(&self) {
+        self.table.clear();
+        self.history.clear();
+    }
+
+    /// Searches `board` within `limits`, returning the result of the
+    /// deepest iteration to complete.
+    pub fn search(&mut self, board: &Board, limits: &Limits) -> SearchResult {
+        self.search_with_progress(board, limits, |_| {})
+    }
+
+    /// As [`Self::search`], but passes the result of every
+    /// completed iteration to `progress` as it goes.
+    pub fn search_with_progress(
+        &mut self,
+        board: &Board,
+        limits: &Limits,
+        mut progress: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        self.stop.store(false, Ordering::Relaxed);
+        self.table.new_search();
+        self.history.age();
+
+        let mut worker = Worker {
+            table: &self.table,
+            history: &self.history,
+            weights: &self.weights,
+            stop: &self.stop,
+            limits,
+            start: Instant::now(),
+            nodes: 0,
+            stopped: false,
+            killers: [[None; 2]; MAX_PLY],
+            pv: vec![MoveList::new(); MAX_PLY + 1],
+        };
+
+        // if not even the first iteration completes, any legal move will do.
+        let mut result = SearchResult {
+            best_move: board.legal_moves().first().copied(),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            time: Duration::ZERO,
+            pv: Vec::new(),
+        };
+        if result.best_move.is_none() {
+            return result;
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        let max_depth = limits.depth.unwrap_or(u8::MAX).min(MAX_PLY as u8 - 1);
+        for depth in 1..=max_depth {
+            let score = worker.aspiration(board, depth, result.score);
+            if worker.stopped {
+                break;
+            }
+            result = SearchResult {
+                best_move: worker.pv[0].first().copied(),
+                score,
+                depth,
+                nodes: worker.nodes,
+                time: worker.start.elapsed(),
+                pv: worker.pv[0].to_vec(),
+            };
+            progress(&result);
+
+            // a forced win or loss won't change with more depth, and an
+            // iteration that would overrun the time limit may as well not start.
+            let finished =
+                plies_to_win(score).is_some_and(|plies| plies.unsigned_abs() <= u32::from(depth));
+            let out_of_time = limits.time.is_some_and(|time| result.time * 2 > time);
+            if finished || out_of_time {
+                break;
+            }
+        }
+        result.nodes = worker.nodes;
+        result.time = worker.start.elapsed();
+        result
+    }
+}
+
+/// The state of a single search.
+struct Worker<'a> {
+    table: &'a TranspositionTable<SearchEntry>,
+    history: &'a History,
+    weights: &'a Weights,
+    stop: &'a AtomicBool,
+    limits: &'a Limits,
+    start: Instant,
+    nodes: u64,
+    /// Set once any limit has been reached, after which every score is meaningless.
+    stopped: bool,
+    /// Two walls per ply that recently caused cutoffs.
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    /// The principal variation from each ply, as a triangular table.
+    pv: Vec<MoveList>,
+}
+
+impl Worker<'_> {
+    /// Searches the root to `depth` with a narrow window around `previous`,
+    /// the score of the last iteration, widening the window on failure.
+    fn aspiration(&mut self, board: &Board, depth: u8, previous: i32) -> i32 {
+        let depth = i32::from(depth);
+        if depth < 4 {
+            return self.negamax(board, depth, -INFINITY, INFINITY, 0);
+        }
+        let mut window = 40;
+        let (mut alpha, mut beta) = (previous - window, previous + window);
+        loop {
+            let score = self.negamax(board, depth, alpha, beta, 0);
+            if self.stopped {
+                return score;
+            }
+            if score <= alpha {
+                alpha = (score - window).max(-INFINITY);
+            } else if score >= beta {
+                beta = (score + window).min(INFINITY);
+            } else {
+                return score;
+            }
+            window *= 2;
+        }
+    }
+
+    /// Determines whether the search has hit any of its limits.
+    fn should_stop(&mut self) -> bool {
+        if !self.stopped && self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.stopped = self.stop.load(Ordering::Relaxed)
+                || self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes)
+                || self
+                    .limits
+                    .time
+                    .is_some_and(|time| self.start.elapsed() >= time);
+        }
+        self.stopped
+    }
+
+    /// Records `mv` followed by the principal variation of the next ply
+    /// as the principal variation of `ply`.
+    fn update_pv(&mut self, ply: usize, mv: Move) {
+        let (this, rest) = self.pv.split_at_mut(ply + 1);
+        let pv = &mut this[ply];
+        pv.clear();
+        pv.push(mv);
+        pv.extend(rest[0].iter().copied());
+    }
+
+    /// Rewards the move that caused a cutoff, and penalises the
+    /// walls tried before it, which failed to.
+    fn update_ordering(
+        &mut self,
+        board: &Board,
+        best: Move,
+        tried: &[Move],
+        depth: i32,
+        ply: usize,
+    ) {
+        if matches!(best, Move::Pawn { .. }) {
+            return;
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(best) {
+            killers[1] = killers[0];
+            killers[0] = Some(best);
+        }
+        let bonus = depth * depth;
+        let colour = board.turn();
+        self.history.update(colour, best, bonus);
+        for &mv in tried.iter().filter(|mv| matches!(mv, Move::Wall { .. })) {
+            self.history.update(colour, mv, -bonus);
+        }
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap,
+        clippy::cast_sign_loss
+    )]
+    fn negamax(&mut self, board: &Board, depth: i32, mut alpha: i32, beta: i32, ply: usize) -> i32 {
+        self.pv[ply].clear();
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        // the game ends as soon as a pawn reaches its goal row,
+        // so if the game is over then the side to move has lost.
+        if board.outcome().is_some() {
+            return -(WIN - ply as i32);
+        }
+        if depth <= 0 || ply >= MAX_PLY - 1 {
+            return eval::evaluate(board, self.weights);
+        }
+
+        let pv_node = beta - alpha > 1;
+        let entry = self.table.probe(board.hash_key());
+        if let Some(entry) = entry.filter(|entry| !pv_node && i32::from(entry.depth) >= depth) {
+            let score = score_from_table(entry.score, ply);
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if cutoff {
+                return score;
+            }
+        }
+
+        let history = self.history;
+        let mut picker = MovePicker::new(board, entry.and_then(|entry| entry.best_move))
+            .with_killers(self.killers[ply])
+            .with_history(history);
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        let mut tried = MoveList::new();
+        while let Some(mv) = picker.next() {
+            let mut child = *board;
+            child.make_move(mv);
+
+            // the first move is searched with the full window, and the rest with a null
+            // window, on the expectation that they're worse. any that turn out better
+            // are searched again with the full window to find out by how much.
+            let score = if tried.is_empty() {
+                -self.negamax(&child, depth - 1, -beta, -alpha, ply + 1)
+            } else {
+                let score = -self.negamax(&child, depth - 1, -alpha - 1, -alpha, ply + 1);
+                if score > alpha && score < beta {
+                    -self.negamax(&child, depth - 1, -beta, -alpha, ply + 1)
+                } else {
+                    score
+                }
+            };
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+                if score > alpha {
+                    alpha = score;
+                    self.update_pv(ply, mv);
+                }
+                if score >= beta {
+                    if picker.stage() != Stage::HashMove {
+                        self.update_ordering(board, mv, &tried, depth, ply);
+                    }
+                    break;
+                }
+            }
+            tried.push(mv);
+        }
+
+        // a pawn can be hemmed in by walls and the other pawn,
+        // which leaves no moves at all if it has no walls left.
+        if best_move.is_none() {
+            return 0;
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.table.store(
+            board.hash_key(),
+            SearchEntry {
+                best_move,
+                score: score_to_table(best_score, ply),
+                depth: depth as u8,
+                bound,
+            },
+        );
+        best_score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::atomic::Ordering, time::Duration};
+
+    use super::{plies_to_win, Bound, Limits, SearchEntry, Searcher, WIN};
+    use crate::{board::Board, tt::TableEntry, types::Move};
+
+    #[test]
+    fn entry_round_trip() {
+        for (best_move, score, bound) in [
+            (Some("e2"), -WIN + 3, Bound::Upper),
+            (Some("h8v"), 0, Bound::Exact),
+            (None, 1234, Bound::Lower),
+        ] {
+            let entry = SearchEntry {
+                best_move: best_move.map(|mv| mv.parse::<Move>().unwrap()),
+                score: i16::try_from(score).unwrap(),
+                depth: 17,
+                bound,
+            };
+            assert_eq!(SearchEntry::unpack(entry.pack()), entry);
+        }
+    }
+
+    #[test]
+    fn finds_immediate_win() {
+        let board: Board = " /  / e8 a9 / 0 0 / 1".parse().unwrap();
+        let result = Searcher::new(1).search(&board, &Limits::default());
+        assert_eq!(result.best_move, Some("e9".parse().unwrap()));
+        assert_eq!(result.score, WIN - 1);
+        assert_eq!(plies_to_win(result.score), Some(1));
+        assert_eq!(result.depth, 1);
+    }
+
+    #[test]
+    fn stops_the_opponent_winning() {
+        // black wins next move unless white walls off d1,
+        // and white is too far from the goal row to race.
+        let board: Board = " /  / e2 d2 / 1 0 / 1".parse().unwrap();
+        let limits = Limits {
+            depth: Some(2),
+            ..Limits::default()
+        };
+        let result = Searcher::new(1).search(&board, &limits);
+        let mut child = board;
+        child.make_move(result.best_move.unwrap());
+        assert!(!child.legal_moves().contains("d1".parse().unwrap()));
+        assert_eq!(plies_to_win(result.score), None);
+    }
+
+    #[test]
+    fn depth_limit_and_principal_variation() {
+        let board = Board::default();
+        let limits = Limits {
+            depth: Some(3),
+            ..Limits::default()
+        };
+        let mut searcher = Searcher::new(1);
+        let mut depths = Vec::new();
+        let result = searcher.search_with_progress(&board, &limits, |info| depths.push(info.depth));
+        assert_eq!(depths, [1, 2, 3]);
+        assert_eq!(result.depth, 3);
+        assert_eq!(result.pv.first().copied(), result.best_move);
+        let mut board = board;
+        for &mv in &result.pv {
+            assert!(
+                board.is_legal(mv),
+                "{mv} is illegal in the principal variation"
+            );
+            board.make_move(mv);
+        }
+
+        // the same search from scratch gives the same answer.
+        searcher.clear();
+        assert_eq!(searcher.search(&Board::default(), &limits).pv, result.pv);
+    }
+
+    #[test]
+    fn node_and_time_limits() {
+        let board = Board::default();
+        let mut searcher = Searcher::new(1);
+        let limits = Limits {
+            nodes: Some(5000),
+            ..Limits::default()
+        };
+        let result = searcher.search(&board, &limits);
+        assert!(result.nodes <= 5000 + super::CHECK_INTERVAL);
+        assert!(board.is_legal(result.best_move.unwrap()));
+
+        let limits = Limits {
+            time: Some(Duration::from_millis(50)),
+            ..Limits::default()
+        };
+        let result = searcher.search(&board, &limits);
+        assert!(result.time < Duration::from_secs(1));
+        assert!(board.is_legal(result.best_move.unwrap()));
+    }
+
+    #[test]
+    fn stop_flag() {
+        let mut searcher = Searcher::new(1);
+        let stop = searcher.stop_flag();
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            stop.store(true, Ordering::Relaxed);
+        });
+        let result = searcher.search(&Board::default(), &Limits::default());
+        stopper.join().unwrap();
+        assert!(result.best_move.is_some());
+        assert!(result.time < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,356 @@
+//! `serde` support, enabled by the `serde` feature.
+//!
+//! Human-readable formats, like JSON, get the same text as the `Display`
+//! implementations: `"e4"` for a square, `"e4h"` for a move, and a FEN for a
+//! board. Binary formats get small integers instead, such as a square's
+//! index or a move packed by [`Move::to_u16`].
+
+use std::fmt::Write;
+
+use serde::{de::Error, ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    board::Board,
+    squareset9x9::SquareSet,
+    types::{Colour, Move, Square8x8, Square9x9, WallOrientation},
+};
+
+/// Reads a string and parses it, reporting the string and the parse error on failure.
+fn parse<'de, D, T>(deserializer: D, what: &str) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Debug,
+{
+    let text = String::deserialize(deserializer)?;
+    text.parse()
+        .map_err(|error| D::Error::custom(format_args!("invalid {what} {text:?}: {error:?}")))
+}
+
+impl Serialize for Square9x9 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_u8(self.index())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Square9x9 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            parse(deserializer, "square")
+        } else {
+            let index = u8::deserialize(deserializer)?;
+            Self::from_index(index)
+                .ok_or_else(|| D::Error::custom(format_args!("invalid square index {index}")))
+        }
+    }
+}
+
+impl Serialize for Square8x8 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_u8(self.index())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Square8x8 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            parse(deserializer, "wall square")
+        } else {
+            let index = u8::deserialize(deserializer)?;
+            Self::from_index(index)
+                .ok_or_else(|| D::Error::custom(format_args!("invalid wall square index {index}")))
+        }
+    }
+}
+
+/// Written as `"h"` or `"v"`, as in move notation, or as 0 or 1.
+impl Serialize for WallOrientation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match (serializer.is_human_readable(), self) {
+            (true, Self::Horizontal) => serializer.serialize_str("h"),
+            (true, Self::Vertical) => serializer.serialize_str("v"),
+            (false, Self::Horizontal) => serializer.serialize_u8(0),
+            (false, Self::Vertical) => serializer.serialize_u8(1),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for WallOrientation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            match String::deserialize(deserializer)?.as_str() {
+                "h" => Ok(Self::Horizontal),
+                "v" => Ok(Self::Vertical),
+                other => Err(D::Error::custom(format_args!(
+                    "invalid wall orientation {other:?}"
+                ))),
+            }
+        } else {
+            match u8::deserialize(deserializer)? {
+                0 => Ok(Self::Horizontal),
+                1 => Ok(Self::Vertical),
+                other => Err(D::Error::custom(format_args!(
+                    "invalid wall orientation {other}"
+                ))),
+            }
+        }
+    }
+}
+
+impl Serialize for Move {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_u16(self.to_u16())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Move {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            parse(deserializer, "move")
+        } else {
+            let packed = u16::deserialize(deserializer)?;
+            Self::from_u16(packed)
+                .ok_or_else(|| D::Error::custom(format_args!("invalid packed move {packed}")))
+        }
+    }
+}
+
+/// Written as a list of squares, or as the low and high
+/// 64 bits of the set, with a bit for each square's index.
+impl Serialize for SquareSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            let mut seq = serializer.serialize_seq(Some(self.count() as usize))?;
+            for square in *self {
+                seq.serialize_element(&square)?;
+            }
+            seq.end()
+        } else {
+            #[allow(clippy::cast_possible_truncation)]
+            let words = (self.inner() as u64, (self.inner() >> 64) as u64);
+            words.serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SquareSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let squares = Vec::<Square9x9>::deserialize(deserializer)?;
+            Ok(squares.into_iter().fold(Self::new(), Self::add))
+        } else {
+            let (low, high) = <(u64, u64)>::deserialize(deserializer)?;
+            let inner = u128::from(high) << 64 | u128::from(low);
+            if inner & !Self::ALL_MASK != 0 {
+                return Err(D::Error::custom("square set has squares off the board"));
+            }
+            Ok(Self::from_inner(inner))
+        }
+    }
+}
+
+/// The compact form of a board: a bit for each horizontal wall and for each
+/// vertical wall by the index of its centre, the index of each pawn, the
+/// walls each player has left, and 0 if white is to move or 1 if black is.
+type CompactBoard = (u64, u64, u8, u8, u8, u8, u8);
+
+/// Like a FEN, both forms keep the side to move but not the number of plies played.
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return serializer.collect_str(self);
+        }
+        let walls = |orientation| {
+            (0..64)
+                .filter_map(Square8x8::from_index)
+                .filter(|&to_square| self.has_wall(to_square, orientation))
+                .fold(0_u64, |walls, to_square| walls | 1 << to_square.index())
+        };
+        let compact: CompactBoard = (
+            walls(WallOrientation::Horizontal),
+            walls(WallOrientation::Vertical),
+            self.pawn(Colour::White).index(),
+            self.pawn(Colour::Black).index(),
+            self.walls_in_pocket(Colour::White),
+            self.walls_in_pocket(Colour::Black),
+            u8::from(self.turn() == Colour::Black),
+        );
+        compact.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            return parse(deserializer, "FEN");
+        }
+        let (horizontal, vertical, white, black, white_walls, black_walls, turn): CompactBoard =
+            Deserialize::deserialize(deserializer)?;
+        // the compact form is checked by the same rules as a FEN by writing it as one.
+        let mut fen = String::new();
+        for walls in [horizontal, vertical] {
+            for to_square in (0..64).filter(|index| walls >> index & 1 != 0) {
+                let to_square =
+                    Square8x8::from_index(to_square).expect("the index is on the board");
+                write!(fen, "{to_square}").expect("writing to a string can't fail");
+            }
+            fen.push_str(" / ");
+        }
+        let pawn = |index| {
+            Square9x9::from_index(index)
+                .ok_or_else(|| D::Error::custom(format_args!("invalid pawn index {index}")))
+        };
+        let player = match turn {
+            0 => 1,
+            1 => 2,
+            other => {
+                return Err(D::Error::custom(format_args!(
+                    "invalid side to move {other}"
+                )))
+            }
+        };
+        write!(
+            fen,
+            "{} {} / {white_walls} {black_walls} / {player}",
+            pawn(white)?,
+            pawn(black)?
+        )
+        .expect("writing to a string can't fail");
+        Self::from_fen(&fen)
+            .map_err(|error| D::Error::custom(format_args!("invalid board: {error:?}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_test::{assert_de_tokens_error, assert_tokens, Configure, Token};
+
+    use crate::{
+        board::Board,
+        squareset9x9::SquareSet,
+        types::{Move, Square8x8, Square9x9, WallOrientation},
+    };
+
+    #[test]
+    fn squares_and_moves() {
+        let square: Square9x9 = "e4".parse().unwrap();
+        assert_tokens(&square.readable(), &[Token::Str("e4")]);
+        assert_tokens(&square.compact(), &[Token::U8(31)]);
+
+        let wall_square: Square8x8 = "h8".parse().unwrap();
+        assert_tokens(&wall_square.readable(), &[Token::Str("h8")]);
+        assert_tokens(&wall_square.compact(), &[Token::U8(63)]);
+
+        assert_tokens(&WallOrientation::Vertical.readable(), &[Token::Str("v")]);
+        assert_tokens(&WallOrientation::Vertical.compact(), &[Token::U8(1)]);
+
+        let mv: Move = "e4h".parse().unwrap();
+        assert_tokens(&mv.readable(), &[Token::Str("e4h")]);
+        assert_tokens(&mv.compact(), &[Token::U16(mv.to_u16())]);
+        let mv: Move = "e2".parse().unwrap();
+        assert_tokens(&mv.readable(), &[Token::Str("e2")]);
+        assert_tokens(&mv.compact(), &[Token::U16(0b1000_1101)]);
+
+        assert_de_tokens_error::<serde_test::Readable<Square9x9>>(
+            &[Token::Str("j1")],
+            "invalid square \"j1\": FileOutOfRange('j')",
+        );
+        assert_de_tokens_error::<serde_test::Compact<Square9x9>>(
+            &[Token::U8(81)],
+            "invalid square index 81",
+        );
+        assert_de_tokens_error::<serde_test::Compact<Move>>(
+            &[Token::U16(0)],
+            "invalid packed move 0",
+        );
+    }
+
+    #[test]
+    fn square_sets() {
+        let set = ["a1", "e5", "i9"]
+            .into_iter()
+            .map(|square| square.parse().unwrap())
+            .fold(SquareSet::new(), SquareSet::add);
+        assert_tokens(
+            &set.readable(),
+            &[
+                Token::Seq { len: Some(3) },
+                Token::Str("a1"),
+                Token::Str("e5"),
+                Token::Str("i9"),
+                Token::SeqEnd,
+            ],
+        );
+        assert_tokens(
+            &set.compact(),
+            &[
+                Token::Tuple { len: 2 },
+                Token::U64(1 | 1 << 40),
+                Token::U64(1 << 16),
+                Token::TupleEnd,
+            ],
+        );
+        assert_de_tokens_error::<serde_test::Compact<SquareSet>>(
+            &[
+                Token::Tuple { len: 2 },
+                Token::U64(0),
+                Token::U64(1 << 17),
+                Token::TupleEnd,
+            ],
+            "square set has squares off the board",
+        );
+    }
+
+    #[test]
+    fn boards() {
+        let fen = "c3e8 / d5 / e7 e9 / 8 9 / 2";
+        let board: Board = fen.parse().unwrap();
+        assert_tokens(&board.readable(), &[Token::Str(fen)]);
+        assert_tokens(
+            &board.compact(),
+            &[
+                Token::Tuple { len: 7 },
+                Token::U64(1 << 60 | 1 << 18),
+                Token::U64(1 << 35),
+                Token::U8(58),
+                Token::U8(76),
+                Token::U8(8),
+                Token::U8(9),
+                Token::U8(1),
+                Token::TupleEnd,
+            ],
+        );
+
+        // overlapping walls are rejected in both forms.
+        assert_de_tokens_error::<serde_test::Readable<Board>>(
+            &[Token::Str("e8f8 /  / e1 e9 / 10 10 / 1")],
+            "invalid FEN \"e8f8 /  / e1 e9 / 10 10 / 1\": OverlappingWall(Wall { to_square: Square8x8(61), orientation: Horizontal })",
+        );
+        assert_de_tokens_error::<serde_test::Compact<Board>>(
+            &[
+                Token::Tuple { len: 7 },
+                Token::U64(1 << 60 | 1 << 61),
+                Token::U64(0),
+                Token::U8(4),
+                Token::U8(76),
+                Token::U8(10),
+                Token::U8(10),
+                Token::U8(0),
+                Token::TupleEnd,
+            ],
+            "invalid board: OverlappingWall(Wall { to_square: Square8x8(61), orientation: Horizontal })",
+        );
+    }
+}
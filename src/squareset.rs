@@ -1,5 +1,5 @@
 use std::{ops::{
-    BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr, Sub, SubAssign,
+    BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Sub, SubAssign,
 }, fmt::Display};
 
 use crate::types::Square8x8;
@@ -10,6 +10,7 @@ pub struct SquareSet {
     inner: u64,
 }
 
+#[allow(dead_code)]
 pub static BB_RANKS: [SquareSet; 8] = [
     SquareSet::RANK_1,
     SquareSet::RANK_2,
@@ -21,6 +22,7 @@ pub static BB_RANKS: [SquareSet; 8] = [
     SquareSet::RANK_8,
 ];
 
+#[allow(dead_code)]
 pub static BB_FILES: [SquareSet; 8] = [
     SquareSet::FILE_A,
     SquareSet::FILE_B,
@@ -36,23 +38,39 @@ impl SquareSet {
     pub const EMPTY: Self = Self { inner: 0 };
     pub const FULL: Self = Self { inner: !0 };
 
+    #[allow(dead_code)]
     pub const RANK_1: Self = Self { inner: 0x0000_0000_0000_00FF };
+    #[allow(dead_code)]
     pub const RANK_2: Self = Self { inner: 0x0000_0000_0000_FF00 };
+    #[allow(dead_code)]
     pub const RANK_3: Self = Self { inner: 0x0000_0000_00FF_0000 };
+    #[allow(dead_code)]
     pub const RANK_4: Self = Self { inner: 0x0000_0000_FF00_0000 };
+    #[allow(dead_code)]
     pub const RANK_5: Self = Self { inner: 0x0000_00FF_0000_0000 };
+    #[allow(dead_code)]
     pub const RANK_6: Self = Self { inner: 0x0000_FF00_0000_0000 };
+    #[allow(dead_code)]
     pub const RANK_7: Self = Self { inner: 0x00FF_0000_0000_0000 };
+    #[allow(dead_code)]
     pub const RANK_8: Self = Self { inner: 0xFF00_0000_0000_0000 };
     pub const FILE_A: Self = Self { inner: 0x0101_0101_0101_0101 };
+    #[allow(dead_code)]
     pub const FILE_B: Self = Self { inner: 0x0202_0202_0202_0202 };
+    #[allow(dead_code)]
     pub const FILE_C: Self = Self { inner: 0x0404_0404_0404_0404 };
+    #[allow(dead_code)]
     pub const FILE_D: Self = Self { inner: 0x0808_0808_0808_0808 };
+    #[allow(dead_code)]
     pub const FILE_E: Self = Self { inner: 0x1010_1010_1010_1010 };
+    #[allow(dead_code)]
     pub const FILE_F: Self = Self { inner: 0x2020_2020_2020_2020 };
+    #[allow(dead_code)]
     pub const FILE_G: Self = Self { inner: 0x4040_4040_4040_4040 };
     pub const FILE_H: Self = Self { inner: 0x8080_8080_8080_8080 };
+    #[allow(dead_code)]
     pub const LIGHT_SQUARES: Self = Self { inner: 0x55AA_55AA_55AA_55AA };
+    #[allow(dead_code)]
     pub const DARK_SQUARES: Self = Self { inner: 0xAA55_AA55_AA55_AA55 };
 
     pub const fn from_inner(inner: u64) -> Self {
@@ -63,14 +81,17 @@ impl SquareSet {
         self.inner
     }
 
+    #[allow(dead_code)]
     pub const fn count(self) -> u32 {
         self.inner.count_ones()
     }
 
+    #[allow(dead_code)]
     pub const fn is_empty(self) -> bool {
         self.inner == 0
     }
 
+    #[allow(dead_code)]
     pub const fn is_full(self) -> bool {
         self.inner == !0
     }
@@ -79,10 +100,12 @@ impl SquareSet {
         self.inner != 0
     }
 
+    #[allow(dead_code)]
     pub const fn intersection(self, other: Self) -> Self {
         Self { inner: self.inner & other.inner }
     }
 
+    #[allow(dead_code)]
     pub const fn contains(self, other: Self) -> bool {
         (self.inner & other.inner) == other.inner
     }
@@ -91,6 +114,7 @@ impl SquareSet {
         (self.inner & (1 << square.index())) != 0
     }
 
+    #[allow(dead_code)]
     pub const fn union(self, other: Self) -> Self {
         Self { inner: self.inner | other.inner }
     }
@@ -99,6 +123,7 @@ impl SquareSet {
         Self { inner: self.inner | (1 << square.index()) }
     }
 
+    #[allow(dead_code)]
     pub const fn remove(self, other: Self) -> Self {
         Self { inner: self.inner & !other.inner }
     }
@@ -107,33 +132,40 @@ impl SquareSet {
         Self { inner: self.inner & !(1 << square.index()) }
     }
 
+    #[allow(dead_code)]
     pub const fn toggle(self, other: Self) -> Self {
         Self { inner: self.inner ^ other.inner }
     }
 
+    #[allow(dead_code)]
     pub const fn toggle_square(self, square: Square8x8) -> Self {
         Self { inner: self.inner ^ (1 << square.index()) }
     }
 
-    #[allow(clippy::cast_possible_truncation)]
+    #[allow(dead_code, clippy::cast_possible_truncation)]
     pub const fn first(self) -> Square8x8 {
         debug_assert!(self.inner != 0, "Tried to get first square of empty bitboard");
         unsafe { Square8x8::from_index_unchecked(self.inner.trailing_zeros() as u8) }
     }
 
+    #[allow(dead_code)]
     pub const fn from_square(square: Square8x8) -> Self {
         Self { inner: 1 << square.index() }
     }
 
+    #[allow(dead_code)]
     pub fn north_east_one(self) -> Self {
         Self { inner: self.inner << 9 } & !Self::FILE_A
     }
+    #[allow(dead_code)]
     pub fn north_west_one(self) -> Self {
         Self { inner: self.inner << 7 } & !Self::FILE_H
     }
+    #[allow(dead_code)]
     pub fn south_east_one(self) -> Self {
         Self { inner: self.inner >> 7 } & !Self::FILE_A
     }
+    #[allow(dead_code)]
     pub fn south_west_one(self) -> Self {
         Self { inner: self.inner >> 9 } & !Self::FILE_H
     }
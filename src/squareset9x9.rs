@@ -2,7 +2,7 @@ use std::ops::{
     BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr, Sub, SubAssign,
 };
 
-use crate::types::Square;
+use crate::types::Square9x9;
 
 /// Represents an occupancy.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
@@ -43,7 +43,14 @@ impl SquareSet {
         Self { inner: mask }
     };
 
+    pub const RANK_1: Self = Self { inner: 0x1FF };
+
+    pub const RANK_9: Self = Self {
+        inner: 0x1FF << (9 * 8),
+    };
+
     /// Creates a new empty `SquareSet`.
+    #[must_use]
     pub const fn new() -> Self {
         Self { inner: 0 }
     }
@@ -53,20 +60,54 @@ impl SquareSet {
         Self { inner }
     }
 
+    /// Creates a new `SquareSet` from its raw representation.
+    /// Bits outside the board are discarded.
+    #[must_use]
+    pub const fn from_inner(inner: u128) -> Self {
+        Self::with(inner & Self::ALL_MASK)
+    }
+
+    /// Returns the raw representation of the set.
+    #[must_use]
+    pub const fn inner(self) -> u128 {
+        self.inner
+    }
+
+    /// Creates a new `SquareSet` containing only the given square.
+    #[must_use]
+    pub const fn from_square(square: Square9x9) -> Self {
+        Self::with(1 << square.index())
+    }
+
+    /// Returns whether the set is empty.
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.inner == 0
+    }
+
+    /// Returns the number of squares in the set.
+    #[must_use]
+    pub const fn count(self) -> u32 {
+        self.inner.count_ones()
+    }
+
     /// Returns whether the given square is occupied.
-    pub const fn contains(self, square: Square) -> bool {
+    #[must_use]
+    pub const fn contains(self, square: Square9x9) -> bool {
         self.inner & (1 << square.index()) != 0
     }
 
     /// Adds the given square to the set.
-    pub const fn add(self, square: Square) -> Self {
+    #[must_use]
+    pub const fn add(self, square: Square9x9) -> Self {
         Self {
             inner: self.inner | 1 << square.index(),
         }
     }
 
     /// Removes the given square from the set.
-    pub const fn remove(self, square: Square) -> Self {
+    #[must_use]
+    pub const fn remove(self, square: Square9x9) -> Self {
         Self {
             inner: self.inner & !(1 << square.index()),
         }
@@ -74,6 +115,7 @@ impl SquareSet {
 
     /// Shift the squares up by one.
     /// Squares on the top row are removed.
+    #[must_use]
     pub const fn north_one(self) -> Self {
         Self {
             inner: (self.inner << 9) & Self::ALL_MASK,
@@ -82,6 +124,7 @@ impl SquareSet {
 
     /// Shift the squares down by one.
     /// Squares on the bottom row are removed.
+    #[must_use]
     pub const fn south_one(self) -> Self {
         Self {
             inner: self.inner >> 9,
@@ -90,6 +133,7 @@ impl SquareSet {
 
     /// Shift the squares left by one.
     /// Squares on the leftmost column are removed.
+    #[must_use]
     pub const fn west_one(self) -> Self {
         Self {
             inner: self.inner >> 1,
@@ -99,6 +143,7 @@ impl SquareSet {
 
     /// Shift the squares right by one.
     /// Squares on the rightmost column are removed.
+    #[must_use]
     pub const fn east_one(self) -> Self {
         Self {
             inner: self.inner << 1,
@@ -106,12 +151,14 @@ impl SquareSet {
         .intersection(Self::A_FILE.complement())
     }
 
+    #[must_use]
     pub const fn complement(self) -> Self {
         Self {
             inner: !self.inner & Self::ALL_MASK,
         }
     }
 
+    #[must_use]
     pub const fn intersection(self, other: Self) -> Self {
         Self {
             inner: self.inner & other.inner,
@@ -218,7 +265,7 @@ impl std::fmt::Display for SquareSet {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for rank in (0..9).rev() {
             for file in 0..9 {
-                let square = Square::from_file_rank(file, rank).unwrap();
+                let square = Square9x9::from_file_rank(file, rank).unwrap();
                 if self.contains(square) {
                     write!(f, "X ")?;
                 } else {
@@ -236,7 +283,7 @@ impl std::fmt::Display for SquareSet {
 }
 
 impl IntoIterator for SquareSet {
-    type Item = Square;
+    type Item = Square9x9;
     type IntoIter = SquareSetIter;
 
     fn into_iter(self) -> Self::IntoIter {
@@ -249,7 +296,7 @@ pub struct SquareSetIter {
 }
 
 impl Iterator for SquareSetIter {
-    type Item = Square;
+    type Item = Square9x9;
 
     fn next(&mut self) -> Option<Self::Item> {
         #![allow(clippy::cast_possible_truncation)]
@@ -258,7 +305,7 @@ impl Iterator for SquareSetIter {
         } else {
             let index = self.inner.trailing_zeros() as u8;
             self.inner &= self.inner - 1;
-            Some(unsafe { Square::from_index_unchecked(index) })
+            Some(unsafe { Square9x9::from_index_unchecked(index) })
         }
     }
 }
@@ -267,13 +314,15 @@ impl Iterator for SquareSetIter {
 const _A_FILE_SENSIBLE: () = assert!(SquareSet::A_FILE.inner & !SquareSet::ALL_MASK == 0);
 #[allow(clippy::assertions_on_constants)]
 const _I_FILE_SENSIBLE: () = assert!(SquareSet::I_FILE.inner & !SquareSet::ALL_MASK == 0);
+#[allow(clippy::assertions_on_constants)]
+const _RANK_9_SENSIBLE: () = assert!(SquareSet::RANK_9.inner & !SquareSet::ALL_MASK == 0);
 
 mod tests {
     #[test]
     fn squareset_add_remove() {
-        use super::{Square, SquareSet};
+        use super::{Square9x9, SquareSet};
 
-        let a1: Square = "a1".parse().unwrap();
+        let a1: Square9x9 = "a1".parse().unwrap();
         let mut set = SquareSet::new();
         assert!(!set.contains(a1));
         set = set.add(a1);
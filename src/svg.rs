@@ -0,0 +1,310 @@
+//! SVG images of boards, for reports and documentation.
+//!
+//! An [`Svg`] draws the grid with its coordinates, both pawns and every
+//! wall placed, and can be annotated with moves, a set of shaded squares
+//! such as a shortest path, and a heatmap over the squares. The image is
+//! written as a standalone SVG document by its `Display` implementation.
+
+use std::fmt::Display;
+
+use crate::{
+    board::Board,
+    squareset9x9::SquareSet as SquareSet9x9,
+    types::{Colour, Move, Square8x8, Square9x9, WallOrientation},
+};
+
+/// The width and height of a square, in pixels.
+const CELL: u32 = 40;
+/// The space around the grid for the coordinates, in pixels.
+const MARGIN: u32 = 24;
+/// The thickness of a wall, in pixels.
+const WALL: u32 = 6;
+/// The width and height of the whole image, in pixels.
+const SIZE: u32 = 2 * MARGIN + 9 * CELL;
+
+/// An SVG image of a board, built with [`Svg::new`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Svg<'a> {
+    board: &'a Board,
+    moves: Vec<Move>,
+    path: SquareSet9x9,
+    heatmap: Option<[f64; 81]>,
+}
+
+impl<'a> Svg<'a> {
+    /// Creates an image of `board` with no annotations.
+    #[must_use]
+    pub const fn new(board: &'a Board) -> Self {
+        Self {
+            board,
+            moves: Vec::new(),
+            path: SquareSet9x9::new(),
+            heatmap: None,
+        }
+    }
+
+    /// Adds a move to the image. A pawn move is drawn as an arrow from the
+    /// pawn of the side to move, and a wall move as a wall in the arrows' colour.
+    #[must_use]
+    pub fn with_move(mut self, mv: Move) -> Self {
+        self.moves.push(mv);
+        self
+    }
+
+    /// Shades the given squares, such as those of a shortest path.
+    #[must_use]
+    pub const fn with_path(mut self, path: SquareSet9x9) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// Shades every square in proportion to its value, indexed by
+    /// [`Square9x9::index`]. Values are clamped to lie between zero,
+    /// which leaves a square unshaded, and one, which shades it fully.
+    #[must_use]
+    pub const fn with_heatmap(mut self, heatmap: [f64; 81]) -> Self {
+        self.heatmap = Some(heatmap);
+        self
+    }
+
+    /// Returns the coordinates of the top-left corner of the square at
+    /// `file` and `rank`.
+    const fn corner(file: u8, rank: u8) -> (u32, u32) {
+        (
+            MARGIN + file as u32 * CELL,
+            MARGIN + (8 - rank as u32) * CELL,
+        )
+    }
+
+    /// Returns the coordinates of the centre of a square.
+    const fn centre(square: Square9x9) -> (u32, u32) {
+        let (x, y) = Self::corner(square.file(), square.rank());
+        (x + CELL / 2, y + CELL / 2)
+    }
+
+    fn squares(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for index in 0..81 {
+            let square = Square9x9::from_index(index).expect("the index is on the board");
+            let (x, y) = Self::corner(square.file(), square.rank());
+            let fill = if self.path.contains(square) {
+                "#9fc5e8"
+            } else {
+                "#f3e5c8"
+            };
+            writeln!(
+                f,
+                r#"<rect x="{x}" y="{y}" width="{CELL}" height="{CELL}" fill="{fill}"/>"#
+            )?;
+            let Some(heatmap) = self.heatmap else {
+                continue;
+            };
+            let value = heatmap[usize::from(index)].clamp(0.0, 1.0);
+            if value > 0.0 {
+                writeln!(
+                    f,
+                    r##"<rect x="{x}" y="{y}" width="{CELL}" height="{CELL}" fill="#d62728" fill-opacity="{value:.2}"/>"##
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn grid(f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in 0..=9 {
+            let offset = MARGIN + line * CELL;
+            let end = MARGIN + 9 * CELL;
+            writeln!(
+                f,
+                r##"<line x1="{offset}" y1="{MARGIN}" x2="{offset}" y2="{end}" stroke="#8b7355"/>"##
+            )?;
+            writeln!(
+                f,
+                r##"<line x1="{MARGIN}" y1="{offset}" x2="{end}" y2="{offset}" stroke="#8b7355"/>"##
+            )?;
+        }
+        Ok(())
+    }
+
+    fn coordinates(f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let style = r#"font-family="sans-serif" font-size="14" text-anchor="middle""#;
+        for (index, file) in ('a'..='i').enumerate() {
+            #[allow(clippy::cast_possible_truncation)]
+            let x = MARGIN + index as u32 * CELL + CELL / 2;
+            let (top, bottom) = (MARGIN - 8, SIZE - 8);
+            writeln!(f, r#"<text x="{x}" y="{top}" {style}>{file}</text>"#)?;
+            writeln!(f, r#"<text x="{x}" y="{bottom}" {style}>{file}</text>"#)?;
+        }
+        for rank in 0..9 {
+            let y = MARGIN + (8 - rank) * CELL + CELL / 2 + 5;
+            let (left, right) = (MARGIN / 2, SIZE - MARGIN / 2);
+            let number = rank + 1;
+            writeln!(f, r#"<text x="{left}" y="{y}" {style}>{number}</text>"#)?;
+            writeln!(f, r#"<text x="{right}" y="{y}" {style}>{number}</text>"#)?;
+        }
+        Ok(())
+    }
+
+    /// Draws a wall along the two edges it blocks.
+    fn wall(
+        f: &mut std::fmt::Formatter<'_>,
+        to_square: Square8x8,
+        orientation: WallOrientation,
+        fill: &str,
+    ) -> std::fmt::Result {
+        let (x, y) = Self::corner(to_square.file(), to_square.rank());
+        let (x, y, width, height) = match orientation {
+            // along the top of the wall's square and the square to its east.
+            WallOrientation::Horizontal => (x, y - WALL / 2, 2 * CELL, WALL),
+            // along the east side of the wall's square and the square to its north.
+            WallOrientation::Vertical => (x + CELL - WALL / 2, y - CELL, WALL, 2 * CELL),
+        };
+        writeln!(
+            f,
+            r#"<rect x="{x}" y="{y}" width="{width}" height="{height}" rx="2" fill="{fill}"/>"#
+        )
+    }
+
+    fn walls(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for index in 0..64 {
+            let to_square = Square8x8::from_index(index).expect("the index is on the board");
+            for orientation in [WallOrientation::Horizontal, WallOrientation::Vertical] {
+                if self.board.has_wall(to_square, orientation) {
+                    Self::wall(f, to_square, orientation, "#5b3a1a")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn pawns(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (colour, fill) in [(Colour::White, "#ffffff"), (Colour::Black, "#202020")] {
+            let (x, y) = Self::centre(self.board.pawn(colour));
+            let radius = CELL * 3 / 8;
+            writeln!(
+                f,
+                r##"<circle cx="{x}" cy="{y}" r="{radius}" fill="{fill}" stroke="#202020" stroke-width="2"/>"##
+            )?;
+        }
+        Ok(())
+    }
+
+    fn moves(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let from = Self::centre(self.board.pawn(self.board.turn()));
+        for &mv in &self.moves {
+            match mv {
+                Move::Pawn { to_square } => {
+                    let to = Self::centre(to_square);
+                    writeln!(
+                        f,
+                        r##"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="#2ca02c" stroke-width="4" stroke-opacity="0.8" marker-end="url(#arrowhead)"/>"##,
+                        from.0, from.1, to.0, to.1
+                    )?;
+                }
+                Move::Wall {
+                    to_square,
+                    orientation,
+                } => Self::wall(f, to_square, orientation, "#2ca02c")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Svg<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{SIZE}" height="{SIZE}" viewBox="0 0 {SIZE} {SIZE}">"#
+        )?;
+        if !self.moves.is_empty() {
+            writeln!(
+                f,
+                r##"<defs><marker id="arrowhead" viewBox="0 0 10 10" refX="8" refY="5" markerWidth="4" markerHeight="4" orient="auto"><path d="M 0 0 L 10 5 L 0 10 z" fill="#2ca02c"/></marker></defs>"##
+            )?;
+        }
+        writeln!(
+            f,
+            r##"<rect width="{SIZE}" height="{SIZE}" fill="#e8d5a9"/>"##
+        )?;
+        self.squares(f)?;
+        Self::grid(f)?;
+        Self::coordinates(f)?;
+        self.walls(f)?;
+        self.pawns(f)?;
+        self.moves(f)?;
+        writeln!(f, "</svg>")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Svg;
+    use crate::{board::Board, types::Colour};
+
+    fn start() -> String {
+        Svg::new(&Board::default()).to_string()
+    }
+
+    fn walls_and_path() -> String {
+        let board: Board = "e8c3 / d5 / e7 e9 / 8 9 / 2".parse().unwrap();
+        Svg::new(&board)
+            .with_path(board.shortest_path(Colour::Black))
+            .with_move("d9".parse().unwrap())
+            .with_move("f9".parse().unwrap())
+            .with_move("a1v".parse().unwrap())
+            .to_string()
+    }
+
+    fn heatmap() -> String {
+        let board: Board = " /  / e4 e6 / 10 10 / 1".parse().unwrap();
+        let mut heatmap = [0.0; 81];
+        for (index, value) in heatmap.iter_mut().enumerate() {
+            #[allow(clippy::cast_precision_loss)]
+            let rank = (index / 9) as f64;
+            *value = rank / 8.0;
+        }
+        Svg::new(&board).with_heatmap(heatmap).to_string()
+    }
+
+    #[test]
+    fn snapshots() {
+        assert_eq!(start(), include_str!("../snapshots/start.svg"));
+        assert_eq!(
+            walls_and_path(),
+            include_str!("../snapshots/walls_and_path.svg")
+        );
+        assert_eq!(heatmap(), include_str!("../snapshots/heatmap.svg"));
+    }
+
+    #[test]
+    fn annotations() {
+        let board = Board::default();
+        let plain = Svg::new(&board).to_string();
+        assert!(plain.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(plain.ends_with("</svg>\n"));
+        assert!(!plain.contains("marker"));
+        assert_eq!(plain.matches("<circle").count(), 2);
+
+        // heat outside zero to one is clamped.
+        let mut heatmap = [0.0; 81];
+        heatmap[0] = -1.0;
+        heatmap[1] = 0.5;
+        heatmap[2] = 3.0;
+        let heated = Svg::new(&board).with_heatmap(heatmap).to_string();
+        assert_eq!(heated.matches("fill-opacity").count(), 2);
+        assert!(heated.contains(r#"fill-opacity="0.50""#));
+        assert!(heated.contains(r#"fill-opacity="1.00""#));
+    }
+
+    /// Rewrites the snapshot files from the current output, after a change
+    /// to the images has been checked by eye.
+    #[test]
+    #[ignore = "overwrites the snapshot files"]
+    fn write_snapshots() {
+        std::fs::create_dir_all("snapshots").unwrap();
+        std::fs::write("snapshots/start.svg", start()).unwrap();
+        std::fs::write("snapshots/walls_and_path.svg", walls_and_path()).unwrap();
+        std::fs::write("snapshots/heatmap.svg", heatmap()).unwrap();
+    }
+}
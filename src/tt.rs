@@ -0,0 +1,314 @@
+//! A fixed-size, lock-free transposition table.
+//!
+//! The table is an array of cache-line-sized buckets, each holding four slots.
+//! A slot stores a 64-bit data word alongside the XOR of that word with the
+//! position's key. Reads and writes are not synchronised, so a slot may be
+//! torn by two threads writing at once, but a torn slot fails the XOR check
+//! on the next probe and is treated as a miss. This is the scheme described
+//! by Hyatt and Mann, and lets the table be shared between threads freely.
+
+use std::{
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, AtomicU8, Ordering},
+};
+
+/// A value that can be stored in a [`TranspositionTable`].
+pub trait TableEntry: Copy {
+    /// Packs the entry into the low 56 bits of a `u64`.
+    /// The high 8 bits are reserved for the table's bookkeeping.
+    fn pack(self) -> u64;
+    /// Unpacks an entry from the low 56 bits of a `u64`.
+    fn unpack(data: u64) -> Self;
+    /// The depth of the search that produced this entry.
+    /// Deeper entries are more expensive to recompute, so are kept in preference to shallower ones.
+    fn depth(self) -> u8;
+}
+
+/// The bits of the data word available to entries.
+const PAYLOAD_MASK: u64 = (1 << 56) - 1;
+/// Set in the data word of every occupied slot.
+const OCCUPIED_BIT: u64 = 1 << 63;
+/// The position of the age field in the data word.
+const AGE_SHIFT: u32 = 56;
+/// The age field is seven bits wide, sitting just below the occupied bit.
+const AGE_MASK: u8 = 0x7F;
+
+const SLOTS_PER_BUCKET: usize = 4;
+
+#[derive(Debug, Default)]
+struct Slot {
+    /// The key of the stored position, XOR-ed with `data`.
+    check: AtomicU64,
+    /// The occupied bit, the age, and the packed entry.
+    data: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+#[repr(align(64))]
+struct Bucket {
+    slots: [Slot; SLOTS_PER_BUCKET],
+}
+
+/// A fixed-size hash table mapping Zobrist keys to entries of type `E`,
+/// safe to probe and store into from many threads at once.
+#[derive(Debug)]
+pub struct TranspositionTable<E> {
+    buckets: Box<[Bucket]>,
+    age: AtomicU8,
+    _entry: PhantomData<fn() -> E>,
+}
+
+impl<E: TableEntry> TranspositionTable<E> {
+    /// Creates a table occupying at most `megabytes` megabytes.
+    /// The number of buckets is rounded down to a power of two,
+    /// and the table always has at least one bucket.
+    #[must_use]
+    pub fn new(megabytes: usize) -> Self {
+        let bytes = megabytes.saturating_mul(1024 * 1024);
+        let buckets = (bytes / std::mem::size_of::<Bucket>()).max(1);
+        // round down to a power of two, so that indexing is a mask.
+        let buckets = 1 << buckets.ilog2();
+        Self {
+            buckets: (0..buckets).map(|_| Bucket::default()).collect(),
+            age: AtomicU8::new(0),
+            _entry: PhantomData,
+        }
+    }
+
+    /// Returns the size of the table in bytes.
+    #[must_use]
+    pub fn size_in_bytes(&self) -> usize {
+        self.buckets.len() * std::mem::size_of::<Bucket>()
+    }
+
+    /// Returns the number of entries the table can hold.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * SLOTS_PER_BUCKET
+    }
+
+    /// Empties the table.
+    pub fn clear(&self) {
+        for slot in self.buckets.iter().flat_map(|bucket| &bucket.slots) {
+            slot.check.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Marks the start of a new search. Entries stored during
+    /// earlier searches are replaced in preference to newer ones.
+    pub fn new_search(&self) {
+        let age = self.age.load(Ordering::Relaxed);
+        self.age
+            .store(age.wrapping_add(1) & AGE_MASK, Ordering::Relaxed);
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn bucket(&self, key: u64) -> &Bucket {
+        // the length is a power of two, so this is a cheap modulo.
+        &self.buckets[key as usize & (self.buckets.len() - 1)]
+    }
+
+    /// Looks up the entry stored for `key`, if any.
+    #[must_use]
+    pub fn probe(&self, key: u64) -> Option<E> {
+        self.bucket(key).slots.iter().find_map(|slot| {
+            let data = slot.data.load(Ordering::Relaxed);
+            let check = slot.check.load(Ordering::Relaxed);
+            (data & OCCUPIED_BIT != 0 && check ^ data == key)
+                .then(|| E::unpack(data & PAYLOAD_MASK))
+        })
+    }
+
+    /// Stores `entry` for `key`, replacing either the existing entry for
+    /// `key` or the least valuable entry in its bucket, judged by depth and age.
+    pub fn store(&self, key: u64, entry: E) {
+        let age = self.age.load(Ordering::Relaxed);
+        let packed = entry.pack();
+        debug_assert_eq!(packed & !PAYLOAD_MASK, 0, "entry overflows 56 bits");
+        let data = OCCUPIED_BIT | (u64::from(age) << AGE_SHIFT) | (packed & PAYLOAD_MASK);
+
+        let bucket = self.bucket(key);
+        let mut victim = &bucket.slots[0];
+        let mut victim_worth = i32::MAX;
+        for slot in &bucket.slots {
+            let old_data = slot.data.load(Ordering::Relaxed);
+            if old_data & OCCUPIED_BIT == 0 || slot.check.load(Ordering::Relaxed) ^ old_data == key
+            {
+                victim = slot;
+                break;
+            }
+            // an entry's worth is its depth, less a penalty for every
+            // search that has started since it was stored.
+            #[allow(clippy::cast_possible_truncation)]
+            let old_age = (old_data >> AGE_SHIFT) as u8 & AGE_MASK;
+            let staleness = age.wrapping_sub(old_age) & AGE_MASK;
+            let old_depth = E::unpack(old_data & PAYLOAD_MASK).depth();
+            let worth = i32::from(old_depth) - 8 * i32::from(staleness);
+            if worth < victim_worth {
+                victim = slot;
+                victim_worth = worth;
+            }
+        }
+
+        victim.check.store(key ^ data, Ordering::Relaxed);
+        victim.data.store(data, Ordering::Relaxed);
+    }
+
+    /// Returns an estimate of how full the table is, in parts per thousand,
+    /// counting only entries stored since the last call to [`Self::new_search`].
+    #[must_use]
+    pub fn hashfull(&self) -> usize {
+        let age = self.age.load(Ordering::Relaxed);
+        let sample = self
+            .buckets
+            .iter()
+            .take(1000)
+            .flat_map(|bucket| &bucket.slots);
+        let (total, full) = sample.fold((0, 0), |(total, full), slot| {
+            let data = slot.data.load(Ordering::Relaxed);
+            #[allow(clippy::cast_possible_truncation)]
+            let current = data & OCCUPIED_BIT != 0 && (data >> AGE_SHIFT) as u8 & AGE_MASK == age;
+            (total + 1, full + usize::from(current))
+        });
+        full * 1000 / total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TableEntry, TranspositionTable};
+
+    /// An entry that records its own key, so that mix-ups can be detected.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    struct TestEntry {
+        tag: u64,
+        depth: u8,
+    }
+
+    impl TableEntry for TestEntry {
+        fn pack(self) -> u64 {
+            (self.tag & 0xFFFF_FFFF_FFFF) | u64::from(self.depth) << 48
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        fn unpack(data: u64) -> Self {
+            Self {
+                tag: data & 0xFFFF_FFFF_FFFF,
+                depth: (data >> 48) as u8,
+            }
+        }
+
+        fn depth(self) -> u8 {
+            self.depth
+        }
+    }
+
+    #[test]
+    fn size_is_power_of_two() {
+        let table = TranspositionTable::<TestEntry>::new(3);
+        assert!(table.size_in_bytes() <= 3 * 1024 * 1024);
+        assert!(table.capacity().is_power_of_two());
+        assert_eq!(table.capacity(), 2 * 1024 * 1024 / 16);
+
+        let table = TranspositionTable::<TestEntry>::new(0);
+        assert_eq!(table.capacity(), 4);
+    }
+
+    #[test]
+    fn store_probe() {
+        let table = TranspositionTable::new(1);
+        let entry = TestEntry {
+            tag: 1234,
+            depth: 5,
+        };
+        assert_eq!(table.probe(0xABCD), None);
+        table.store(0xABCD, entry);
+        assert_eq!(table.probe(0xABCD), Some(entry));
+        // same bucket, different key.
+        assert_eq!(table.probe(0xABCD | 1 << 40), None);
+
+        let newer = TestEntry {
+            tag: 4321,
+            depth: 1,
+        };
+        table.store(0xABCD, newer);
+        assert_eq!(
+            table.probe(0xABCD),
+            Some(newer),
+            "same key always overwrites"
+        );
+
+        table.clear();
+        assert_eq!(table.probe(0xABCD), None);
+    }
+
+    #[test]
+    fn replacement_prefers_shallow_and_stale() {
+        let table = TranspositionTable::new(0);
+        // with a single bucket, every key collides.
+        for (key, depth) in [(1, 10), (2, 3), (3, 7), (4, 9)] {
+            table.store(key, TestEntry { tag: key, depth });
+        }
+        table.store(5, TestEntry { tag: 5, depth: 1 });
+        assert_eq!(table.probe(2), None, "the shallowest entry is replaced");
+        assert!(table.probe(5).is_some());
+
+        // after a couple of searches, even the deep entries become stale,
+        // and are replaced in preference to fresh but shallow ones.
+        table.new_search();
+        table.new_search();
+        table.store(6, TestEntry { tag: 6, depth: 0 });
+        assert_eq!(table.probe(5), None);
+        table.store(7, TestEntry { tag: 7, depth: 0 });
+        assert_eq!(table.probe(3), None, "the stale entry is replaced");
+        for key in [1, 4, 6, 7] {
+            assert!(table.probe(key).is_some(), "{key} should survive");
+        }
+    }
+
+    #[test]
+    fn hashfull() {
+        let table = TranspositionTable::new(1);
+        assert_eq!(table.hashfull(), 0);
+        for key in 0..table.capacity() as u64 {
+            table.store(
+                key.wrapping_mul(0x9E37_79B9_7F4A_7C15),
+                TestEntry { tag: key, depth: 0 },
+            );
+        }
+        assert!(table.hashfull() > 500);
+        table.new_search();
+        assert_eq!(table.hashfull(), 0);
+    }
+
+    #[test]
+    fn concurrent_access() {
+        let table = TranspositionTable::new(0);
+        std::thread::scope(|s| {
+            for thread in 0..4_u64 {
+                let table = &table;
+                s.spawn(move || {
+                    for i in 0..100_000_u64 {
+                        let key = (i % 64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ thread;
+                        table.store(
+                            key,
+                            TestEntry {
+                                tag: key & 0xFFFF_FFFF_FFFF,
+                                depth: 0,
+                            },
+                        );
+                        if let Some(entry) = table.probe(key ^ 1) {
+                            assert_eq!(
+                                entry.tag,
+                                (key ^ 1) & 0xFFFF_FFFF_FFFF,
+                                "torn entry returned"
+                            );
+                        }
+                    }
+                });
+            }
+        });
+    }
+}
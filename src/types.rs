@@ -1,12 +1,64 @@
 use std::str::FromStr;
 use std::fmt::Display;
 
+use crate::squareset9x9::SquareSet as SquareSet9x9;
+
 /// Represents the colour of a pawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Colour {
     White,
     Black,
 }
 
+impl Colour {
+    /// Returns the opposing colour.
+    #[must_use]
+    pub const fn opponent(self) -> Self {
+        match self {
+            Self::White => Self::Black,
+            Self::Black => Self::White,
+        }
+    }
+
+    /// Returns the index of this colour, for indexing into per-player arrays.
+    #[must_use]
+    pub const fn index(self) -> usize {
+        match self {
+            Self::White => 0,
+            Self::Black => 1,
+        }
+    }
+
+    /// Returns the row that this colour's pawn is trying to reach.
+    #[must_use]
+    pub const fn goal_row(self) -> SquareSet9x9 {
+        match self {
+            Self::White => SquareSet9x9::RANK_9,
+            Self::Black => SquareSet9x9::RANK_1,
+        }
+    }
+}
+
+/// Represents the result of a finished game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameResult {
+    /// White's pawn reached the ninth rank.
+    WhiteWin,
+    /// Black's pawn reached the first rank.
+    BlackWin,
+}
+
+impl GameResult {
+    /// Returns the colour of the winning player.
+    #[must_use]
+    pub const fn winner(self) -> Colour {
+        match self {
+            Self::WhiteWin => Colour::White,
+            Self::BlackWin => Colour::Black,
+        }
+    }
+}
+
 /// Represents the orientation of a wall.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum WallOrientation {
@@ -21,37 +73,43 @@ pub struct Square8x8(u8);
 
 impl Square9x9 {
     /// Returns the index of the square.
+    #[must_use]
     pub const fn index(self) -> u8 {
         self.0
     }
 
     /// Determines whether the square is on the edge of the board.
+    #[must_use]
     pub const fn is_edge(self) -> bool {
         self.is_west_edge() || self.is_east_edge() || self.is_north_edge() || self.is_south_edge()
     }
 
     /// Determines whether the square is on the west edge of the board.
+    #[must_use]
     pub const fn is_west_edge(self) -> bool {
         self.file() == 0
     }
 
     /// Determines whether the square is on the east edge of the board.
+    #[must_use]
     pub const fn is_east_edge(self) -> bool {
         self.file() == 8
     }
 
     /// Determines whether the square is on the north edge of the board.
+    #[must_use]
     pub const fn is_north_edge(self) -> bool {
         self.rank() == 8
     }
 
     /// Determines whether the square is on the south edge of the board.
+    #[must_use]
     pub const fn is_south_edge(self) -> bool {
         self.rank() == 0
     }
 
     /// Returns the square at the given index.
-    const fn from_index(index: u8) -> Option<Self> {
+    pub(crate) const fn from_index(index: u8) -> Option<Self> {
         if index < 81 {
             Some(Self(index))
         } else {
@@ -60,16 +118,27 @@ impl Square9x9 {
     }
 
     /// Returns the square at the given index, without checking the index.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be a valid index for the board, i.e. less than 81.
+    #[must_use]
     pub const unsafe fn from_index_unchecked(index: u8) -> Self {
         Self(index)
     }
 
     /// Returns the square given by the file and rank.
+    #[must_use]
     pub const fn from_file_rank(file: u8, rank: u8) -> Option<Self> {
-        Self::from_index(file + rank * 9)
+        if file < 9 && rank < 9 {
+            Self::from_index(file + rank * 9)
+        } else {
+            None
+        }
     }
 
     /// Returns the square below the given square.
+    #[must_use]
     pub const fn below(self) -> Option<Self> {
         if self.0 < 9 {
             None
@@ -79,6 +148,7 @@ impl Square9x9 {
     }
 
     /// Returns the square above the given square.
+    #[must_use]
     pub const fn above(self) -> Option<Self> {
         if self.0 > 71 {
             None
@@ -88,8 +158,9 @@ impl Square9x9 {
     }
 
     /// Returns the square to the left of the given square.
+    #[must_use]
     pub const fn left(self) -> Option<Self> {
-        if self.0 % 9 == 0 {
+        if self.0.is_multiple_of(9) {
             None
         } else {
             Some(Self(self.0 - 1))
@@ -97,6 +168,7 @@ impl Square9x9 {
     }
 
     /// Returns the square to the right of the given square.
+    #[must_use]
     pub const fn right(self) -> Option<Self> {
         if self.0 % 9 == 8 {
             None
@@ -106,11 +178,13 @@ impl Square9x9 {
     }
 
     /// Returns the file of the square.
+    #[must_use]
     pub const fn file(self) -> u8 {
         self.0 % 9
     }
 
     /// Returns the rank of the square.
+    #[must_use]
     pub const fn rank(self) -> u8 {
         self.0 / 9
     }
@@ -118,37 +192,43 @@ impl Square9x9 {
 
 impl Square8x8 {
     /// Returns the index of the square.
+    #[must_use]
     pub const fn index(self) -> u8 {
         self.0
     }
 
     /// Determines whether the square is on the edge of the board.
+    #[must_use]
     pub const fn is_edge(self) -> bool {
         self.is_west_edge() || self.is_east_edge() || self.is_north_edge() || self.is_south_edge()
     }
 
     /// Determines whether the square is on the west edge of the board.
+    #[must_use]
     pub const fn is_west_edge(self) -> bool {
         self.file() == 0
     }
 
     /// Determines whether the square is on the east edge of the board.
+    #[must_use]
     pub const fn is_east_edge(self) -> bool {
         self.file() == 7
     }
 
     /// Determines whether the square is on the north edge of the board.
+    #[must_use]
     pub const fn is_north_edge(self) -> bool {
         self.rank() == 7
     }
 
     /// Determines whether the square is on the south edge of the board.
+    #[must_use]
     pub const fn is_south_edge(self) -> bool {
         self.rank() == 0
     }
 
     /// Returns the square at the given index.
-    const fn from_index(index: u8) -> Option<Self> {
+    pub(crate) const fn from_index(index: u8) -> Option<Self> {
         if index < 64 {
             Some(Self(index))
         } else {
@@ -157,16 +237,27 @@ impl Square8x8 {
     }
 
     /// Returns the square at the given index, without checking the index.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be a valid index for the board, i.e. less than 64.
+    #[must_use]
     pub const unsafe fn from_index_unchecked(index: u8) -> Self {
         Self(index)
     }
 
     /// Returns the square given by the file and rank.
+    #[must_use]
     pub const fn from_file_rank(file: u8, rank: u8) -> Option<Self> {
-        Self::from_index(file + rank * 8)
+        if file < 8 && rank < 8 {
+            Self::from_index(file + rank * 8)
+        } else {
+            None
+        }
     }
 
     /// Returns the square below the given square.
+    #[must_use]
     pub const fn below(self) -> Option<Self> {
         if self.0 < 8 {
             None
@@ -176,6 +267,7 @@ impl Square8x8 {
     }
 
     /// Returns the square above the given square.
+    #[must_use]
     pub const fn above(self) -> Option<Self> {
         if self.0 > 55 {
             None
@@ -185,8 +277,9 @@ impl Square8x8 {
     }
 
     /// Returns the square to the left of the given square.
+    #[must_use]
     pub const fn left(self) -> Option<Self> {
-        if self.0 % 8 == 0 {
+        if self.0.is_multiple_of(8) {
             None
         } else {
             Some(Self(self.0 - 1))
@@ -194,6 +287,7 @@ impl Square8x8 {
     }
 
     /// Returns the square to the right of the given square.
+    #[must_use]
     pub const fn right(self) -> Option<Self> {
         if self.0 % 8 == 7 {
             None
@@ -203,11 +297,13 @@ impl Square8x8 {
     }
 
     /// Returns the file of the square.
+    #[must_use]
     pub const fn file(self) -> u8 {
         self.0 % 8
     }
 
     /// Returns the rank of the square.
+    #[must_use]
     pub const fn rank(self) -> u8 {
         self.0 / 8
     }
@@ -231,6 +327,7 @@ impl std::fmt::Display for Square8x8 {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SquareParseError {
+    NonAscii,
     TooLong(usize),
     TooShort,
     Empty,
@@ -246,6 +343,9 @@ impl FromStr for Square9x9 {
     type Err = SquareParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_ascii() {
+            return Err(SquareParseError::NonAscii);
+        }
         match s.len() {
             0 => return Err(SquareParseError::Empty),
             1 => return Err(SquareParseError::TooShort),
@@ -282,6 +382,9 @@ impl FromStr for Square8x8 {
     type Err = SquareParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_ascii() {
+            return Err(SquareParseError::NonAscii);
+        }
         match s.len() {
             0 => return Err(SquareParseError::Empty),
             1 => return Err(SquareParseError::TooShort),
@@ -327,7 +430,13 @@ pub enum Move {
 }
 
 impl Move {
+    /// The kinds of move, as stored in bits 7 and 8 of a packed move.
+    const PACKED_PAWN: u16 = 1;
+    const PACKED_HORIZONTAL_WALL: u16 = 2;
+    const PACKED_VERTICAL_WALL: u16 = 3;
+
     /// Gets the to-square of a pawn move.
+    #[must_use]
     pub const fn pawn_to_square(self) -> Option<Square9x9> {
         match self {
             Self::Pawn { to_square } => Some(to_square),
@@ -336,12 +445,58 @@ impl Move {
     }
 
     /// Gets the to-square of a wall move.
+    #[must_use]
     pub const fn wall_to_square(self) -> Option<Square8x8> {
         match self {
             Self::Pawn { .. } => None,
             Self::Wall { to_square, .. } => Some(to_square),
         }
     }
+
+    /// Packs the move into the low nine bits of a `u16`.
+    ///
+    /// Bits 0 to 6 hold the index of the move's square: the destination
+    /// of a pawn move, be it a step or a jump, or the centre of a wall.
+    /// Bits 7 and 8 hold the kind of move: 1 for a pawn move, 2 for a
+    /// horizontal wall, and 3 for a vertical wall. No move packs to zero,
+    /// so zero can stand for the absence of a move.
+    #[must_use]
+    pub fn to_u16(self) -> u16 {
+        let (kind, index) = match self {
+            Self::Pawn { to_square } => (Self::PACKED_PAWN, to_square.index()),
+            Self::Wall {
+                to_square,
+                orientation: WallOrientation::Horizontal,
+            } => (Self::PACKED_HORIZONTAL_WALL, to_square.index()),
+            Self::Wall {
+                to_square,
+                orientation: WallOrientation::Vertical,
+            } => (Self::PACKED_VERTICAL_WALL, to_square.index()),
+        };
+        kind << 7 | u16::from(index)
+    }
+
+    /// Unpacks a move packed by [`Self::to_u16`].
+    /// Returns `None` for zero, or for anything else that isn't a valid move.
+    #[must_use]
+    pub fn from_u16(packed: u16) -> Option<Self> {
+        #[allow(clippy::cast_possible_truncation)]
+        let index = (packed & 0x7F) as u8;
+        let wall = |orientation| {
+            Square8x8::from_index(index).map(|to_square| Self::Wall {
+                to_square,
+                orientation,
+            })
+        };
+        match packed >> 7 {
+            Self::PACKED_PAWN => {
+                Square9x9::from_index(index).map(|to_square| Self::Pawn { to_square })
+            }
+            Self::PACKED_HORIZONTAL_WALL => wall(WallOrientation::Horizontal),
+            Self::PACKED_VERTICAL_WALL => wall(WallOrientation::Vertical),
+            _ => None,
+        }
+    }
 }
 
 impl Display for Move {
@@ -369,6 +524,7 @@ impl Display for Move {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MoveParseError {
+    NonAscii,
     Square9x9ParseError(SquareParseError),
     Square8x8ParseError(SquareParseError),
     AlignmentParseError(char),
@@ -381,6 +537,10 @@ impl FromStr for Move {
     type Err = MoveParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // the lengths below are in bytes, so slicing is only safe for ASCII.
+        if !s.is_ascii() {
+            return Err(MoveParseError::NonAscii);
+        }
         match s.len() {
             0 => Err(MoveParseError::Empty),
             1 => Err(MoveParseError::TooShort),
@@ -464,6 +624,7 @@ mod tests {
             "!!".parse::<Square9x9>(),
             Err(SquareParseError::NonAlphabeticFile('!'))
         );
+        assert_eq!("é".parse::<Square9x9>(), Err(SquareParseError::NonAscii));
     }
 
     #[test]
@@ -510,4 +671,39 @@ mod tests {
         assert!(!a2.is_north_edge());
         assert!(!a2.is_south_edge());
     }
+    #[test]
+    fn packed_move_round_trip() {
+        use super::{Move, Square8x8, Square9x9, WallOrientation};
+
+        let mut every_move = Vec::new();
+        for index in 0..81 {
+            let to_square = Square9x9::from_index(index).unwrap();
+            every_move.push(Move::Pawn { to_square });
+        }
+        for index in 0..64 {
+            let to_square = Square8x8::from_index(index).unwrap();
+            for orientation in [WallOrientation::Horizontal, WallOrientation::Vertical] {
+                every_move.push(Move::Wall {
+                    to_square,
+                    orientation,
+                });
+            }
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        for &mv in &every_move {
+            let packed = mv.to_u16();
+            assert!(packed != 0 && packed < 1 << 9, "{mv} packed to {packed:#x}");
+            assert!(seen.insert(packed), "{mv} packed to a duplicate {packed:#x}");
+            assert_eq!(Move::from_u16(packed), Some(mv));
+        }
+
+        // everything else is rejected rather than misread.
+        let valid = (0..=u16::MAX)
+            .filter_map(|packed| Move::from_u16(packed).map(|mv| (packed, mv)))
+            .inspect(|&(packed, mv)| assert_eq!(mv.to_u16(), packed))
+            .count();
+        assert_eq!(valid, every_move.len());
+        assert_eq!(Move::from_u16(0), None);
+    }
 }
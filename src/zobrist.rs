@@ -0,0 +1,36 @@
+//! Pseudorandom keys for Zobrist hashing of board states.
+//!
+//! A board's key is the XOR of the keys for each of its features,
+//! which allows the key to be updated incrementally as moves are made.
+
+use crate::board::Board;
+
+/// Generates `N` pseudorandom keys with the splitmix64 algorithm,
+/// starting from the given seed.
+const fn keys<const N: usize>(seed: u64) -> [u64; N] {
+    let mut state = seed;
+    let mut keys = [0; N];
+    let mut i = 0;
+    while i < N {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+/// Keys for each player's pawn on each of the 81 squares.
+pub static PAWN_KEYS: [[u64; 81]; 2] = [keys(0x5041_574E_0000_0000), keys(0x5041_574E_0000_0001)];
+
+/// Keys for a horizontal and a vertical wall on each of the 64 wall slots.
+pub static WALL_KEYS: [[u64; 64]; 2] = [keys(0x5741_4C4C_0000_0000), keys(0x5741_4C4C_0000_0001)];
+
+/// Keys for each player's count of walls in pocket.
+pub static WALLS_IN_POCKET_KEYS: [[u64; Board::STARTING_WALLS as usize + 1]; 2] =
+    [keys(0x504F_434B_0000_0000), keys(0x504F_434B_0000_0001)];
+
+/// Key for black being the side to move.
+pub const SIDE_TO_MOVE_KEY: u64 = keys::<1>(0x5349_4445_0000_0000)[0];